@@ -0,0 +1,49 @@
+#[macro_use]
+extern crate criterion;
+extern crate easage;
+
+use criterion::Criterion;
+use easage::{packer, Archive, Kind};
+
+const ENTRY_COUNT: usize = 50_000;
+
+fn names() -> Vec<String> {
+    (0..ENTRY_COUNT)
+        .map(|i| format!("Art\\Textures\\Generals\\ZH_{:05}_Texture.dds", i))
+        .collect()
+}
+
+fn archive() -> Archive {
+    let names = names();
+    let entries = names.iter()
+        .map(|name| (name.as_str(), &b"data"[..]))
+        .collect::<Vec<_>>();
+
+    packer::pack(entries, Kind::BigF).unwrap()
+}
+
+fn read_entry_metadata_table(c: &mut Criterion) {
+    let archive = archive();
+
+    c.bench_function("read_entry_metadata_table (50k entries)", |b| {
+        b.iter(|| archive.read_entry_metadata_table().unwrap())
+    });
+}
+
+fn lookup(c: &mut Criterion) {
+    let archive = archive();
+    let table = archive.read_entry_metadata_table().unwrap();
+
+    // Look the names up in an order unrelated to the table's.
+    let names = names();
+    let names = (0..ENTRY_COUNT)
+        .map(|i| names[(i * 7919) % ENTRY_COUNT].clone())
+        .collect::<Vec<_>>();
+
+    c.bench_function("EntryInfoTable::get (50k entries)", |b| {
+        b.iter(|| names.iter().filter(|name| table.get(name.as_str()).is_some()).count())
+    });
+}
+
+criterion_group!(benches, read_entry_metadata_table, lookup);
+criterion_main!(benches);
@@ -1,24 +1,35 @@
 use ::std;
-use std::collections::HashMap;
-use std::io::{self, BufRead, Seek,  SeekFrom};
+use std::io::Read;
 use std::ops::Deref;
 use std::path::Path;
 use std::fs::File;
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
 
-use ::byteorder::{LittleEndian, BigEndian, ReadBytesExt};
+use ::byteorder::{LittleEndian, BigEndian, ByteOrder};
 use ::memmap::{Mmap, MmapOptions};
 use ::owning_ref::ArcRef;
 
-use ::{Result, Error};
+use ::{Result, Error, EntryInfoTable, Limits, HashFn, HashIndex};
+use ::validation::{self, Gap, Issue, Level, ValidationReport};
+use ::checksums::Checksums;
 
 #[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub enum Kind {
     Big4,
     BigF,
+
+    /// Any other 4-byte magic, as used by some modified engines.
+    ///
+    /// Only produced by `from_bytes_lenient` (and so by archives opened with
+    /// `Archive::with_lenient_magic`). The packer writes it like any other kind.
+    Other([u8; 4]),
 }
 
 impl Kind {
+    /// # Errors
+    ///
+    /// * If `bytes` is neither `BIG4` nor `BIGF` this will return `Err(Error::InvalidMagic)`
     pub fn try_from_bytes(bytes: &[u8]) -> Result<Self> {
         match bytes {
             b"BIG4" => Ok(Kind::Big4),
@@ -26,20 +37,132 @@ impl Kind {
             _ => Err(Error::InvalidMagic { magic: bytes.to_vec() }),
         }
     }
-}
 
-/// A map from entry name to metadata present in the header of an Archive.
-pub type EntryInfoTable = HashMap<String, EntryInfo>;
+    /// Like `try_from_bytes` but returns `Kind::Other` for unrecognized magic.
+    ///
+    /// # Errors
+    ///
+    /// * If `bytes` is not exactly 4 bytes long this will return `Err(Error::InvalidMagic)`
+    pub fn from_bytes_lenient(bytes: &[u8]) -> Result<Self> {
+        match Self::try_from_bytes(bytes) {
+            Ok(kind) => Ok(kind),
+            Err(_) if bytes.len() == 4 => Ok(Kind::Other([bytes[0], bytes[1], bytes[2], bytes[3]])),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// The 4 bytes an archive of this kind starts with.
+    pub fn magic(&self) -> [u8; 4] {
+        match *self {
+            Kind::Big4 => *b"BIG4",
+            Kind::BigF => *b"BIGF",
+            Kind::Other(magic) => magic,
+        }
+    }
+}
 
 /// Metadata that describes a single entry
 /// in the owning Archive.
 ///
 /// This struct contains none of the actual file data.
-#[derive(Debug)]
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
 pub struct EntryInfo {
     pub offset: u32,
     pub len: u32,
     pub name: String,
+
+    /// Position of this entry's record in the archive's table (0-based).
+    pub index: usize,
+}
+
+impl EntryInfo {
+    /// Whether this entry marks a directory rather than a file: its name ends
+    /// in `\\` or `/`. Some tools write these, with no data, so that empty
+    /// directories are preserved.
+    ///
+    /// A directory marker that does have data is reported by `Archive::validate`
+    /// and can not be extracted.
+    pub fn is_directory_marker(&self) -> bool {
+        self.name.ends_with(&['\\', '/'][..])
+    }
+}
+
+/// The fixed-size header found at the start of every archive.
+///
+/// See the `read_` functions on `Archive` for a description of each field.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Header {
+    pub kind: Kind,
+    pub size: u32,
+    pub len: u32,
+    pub data_start: u32,
+}
+
+/// The byte order of a `u32` field.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Endianness {
+    Little,
+    Big,
+}
+
+impl Endianness {
+    fn read_u32(self, bytes: &[u8]) -> u32 {
+        match self {
+            Endianness::Little => LittleEndian::read_u32(bytes),
+            Endianness::Big => BigEndian::read_u32(bytes),
+        }
+    }
+}
+
+/// The location of a `u32` header field.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct HeaderField {
+    pub offset: u32,
+    pub endianness: Endianness,
+}
+
+impl HeaderField {
+    pub const fn new(offset: u32, endianness: Endianness) -> Self {
+        HeaderField { offset, endianness }
+    }
+}
+
+/// The layout of an archive's header and table records.
+///
+/// `BIG4` and `BIGF` archives (and any others easage writes) use
+/// `Format::STANDARD`. Some early titles use variants with a longer header or
+/// fields in a different byte order; see `Archive::with_format` to read them.
+/// Everything after the table (secret data and entry data) is the same in
+/// every format.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Format {
+    /// The length of the header. The table starts right after it.
+    pub header_len: u32,
+
+    /// The size of the entire archive.
+    pub size: HeaderField,
+
+    /// The number of entries.
+    pub len: HeaderField,
+
+    /// The offset at which the first entry's data starts.
+    pub data_start: HeaderField,
+
+    /// The byte order of the offset and length at the start of every table record.
+    pub record_endianness: Endianness,
+}
+
+impl Format {
+    /// The 16-byte header of `BIG4` and `BIGF` archives.
+    pub const STANDARD: Format = Format {
+        header_len: 16,
+        size: HeaderField::new(4, Endianness::Little),
+        len: HeaderField::new(8, Endianness::Big),
+        data_start: HeaderField::new(12, Endianness::Big),
+        record_endianness: Endianness::Big,
+    };
 }
 
 #[doc(hidden)]
@@ -60,9 +183,23 @@ macro_rules! check_incomplete {
 /// A file container.
 ///
 /// Library users start here!
-#[derive(Debug, PartialEq)]
+///
+/// # Thread safety
+///
+/// An `Archive` is `Send + Sync`. The underlying memory map is read-only and
+/// every method takes `&self`, so a single `Archive` can be shared between
+/// threads by reference (or in an `Arc`) without any locking.
+///
+/// `clone_handle` creates another `Archive` that shares the same memory map
+/// and cached table (see `table`), for consumers that want an owned value per
+/// thread. Neither the map nor the cached table is ever modified once created.
+#[derive(Debug)]
 pub struct Archive {
     data: ArcRef<Mmap, [u8]>,
+    limits: Limits,
+    lenient_magic: bool,
+    formats: Vec<([u8; 4], Format)>,
+    table: Arc<OnceLock<EntryInfoTable>>,
 }
 
 /// Functions with the `read_` prefix actually perform a read from
@@ -74,17 +211,66 @@ impl Archive {
     #[doc(hidden)]
     pub const HEADER_LEN: u32 = 16;
 
+    /// The smallest possible table record: offset, length, and an empty null-terminated name.
+    const MIN_RECORD_LEN: usize = 4 + 4 + 1;
+
     /// Memory-map the given filepath and initialize an Archive structure.
     ///
     /// This does not perform any data reads and as such performs no archive validation.
     pub fn from_path<P: AsRef<Path>>(path: P) -> Result<Archive> {
         let path = path.as_ref();
-        let file = File::open(path)?;
-        let mmap = unsafe { MmapOptions::new().map(&file)? };
+        let file = File::open(path)
+            .map_err(|e| Error::io(e, "opening", path))?;
+        let mmap = unsafe { MmapOptions::new().map(&file) }
+            .map_err(|e| Error::io(e, "memory-mapping", path))?;
         let mmap = Arc::new(mmap);
         let data = ArcRef::new(mmap).map(|mm| mm.as_ref());
 
-        Ok(Archive { data })
+        Ok(Archive { data, limits: Limits::default(), lenient_magic: false, formats: vec![], table: Arc::default() })
+    }
+
+    /// Read only the 16-byte header from `reader`.
+    ///
+    /// Only `Format::STANDARD` archives can be peeked at.
+    ///
+    /// This is the cheapest way to learn an archive's kind, entry count
+    /// (`len`), and total size: nothing is mapped and the table is not
+    /// parsed. Nothing past the header is read so `reader` does not need to
+    /// be buffered.
+    ///
+    /// # Errors
+    ///
+    /// * If fewer than 16 bytes can be read this will return `Err(Error::IncompleteArchive)`
+    /// * If the magic is not recognized this will return `Err(Error::InvalidMagic)`
+    pub fn peek<R: Read>(reader: R) -> Result<Header> {
+        let header_len = Self::HEADER_LEN as usize;
+        let mut bytes = Vec::with_capacity(header_len);
+        reader.take(Self::HEADER_LEN as u64).read_to_end(&mut bytes)
+            .map_err(|e| Error::IO { inner: e, operation: String::from("reading the header") })?;
+
+        if bytes.len() < header_len {
+            return Err(Error::IncompleteArchive {
+                actual_len: bytes.len(),
+                expected_len: header_len,
+                read_start: 0,
+                read_end: header_len - 1,
+            });
+        }
+
+        Ok(Header {
+            kind: Kind::try_from_bytes(&bytes[0..4])?,
+            size: LittleEndian::read_u32(&bytes[4..8]),
+            len: BigEndian::read_u32(&bytes[8..12]),
+            data_start: BigEndian::read_u32(&bytes[12..16]),
+        })
+    }
+
+    /// Open the file at `path` and `peek` at its header.
+    pub fn peek_path<P: AsRef<Path>>(path: P) -> Result<Header> {
+        let path = path.as_ref();
+        let file = File::open(path)
+            .map_err(|e| Error::io(e, "opening", path))?;
+        Self::peek(file)
     }
 
     /// Create an anonymous memory-map and initialize an Archive structure.
@@ -100,67 +286,222 @@ impl Archive {
         }
 
         let mut mmap_opts = MmapOptions::new();
-        let mut mmap = mmap_opts.len(bytes.len()).map_anon()?;
+        let mut mmap = mmap_opts.len(bytes.len()).map_anon()
+            .map_err(|e| Error::IO { inner: e, operation: String::from("creating an anonymous memory map") })?;
         mmap.copy_from_slice(bytes);
-        let mmap = mmap.make_read_only()?;
+        let mmap = mmap.make_read_only()
+            .map_err(|e| Error::IO { inner: e, operation: String::from("making an anonymous memory map read-only") })?;
         let mmap = Arc::new(mmap);
 
         let data = ArcRef::new(mmap).map(|mm| mm.as_ref());
-        Ok(Archive { data })
+        Ok(Archive { data, limits: Limits::default(), lenient_magic: false, formats: vec![], table: Arc::default() })
     }
 
-    // TODO: Consider returning a Validity enum with Valid, Bogus{Size,Len,Count,Offset}, etc variants
-    #[doc(hidden)]
-    pub fn is_valid(&self) -> bool {
-        // TODOs:
-        // - Check file size (stat) vs `size()`
-        // - Sanity check `len()`
-        // - Check that `data_start() < size()`
-        unimplemented!()
+    /// Enforce `limits` when reading this archive's table.
+    pub fn with_limits(mut self, limits: Limits) -> Self {
+        self.limits = limits;
+        self.table = Arc::default();
+        self
+    }
+
+    /// Accept any magic instead of only `BIG4` and `BIGF`: `read_kind` returns
+    /// `Kind::Other` for magic it does not recognize rather than an error.
+    ///
+    /// Useful for inspecting archives from modified engines. Nothing else
+    /// about how the archive is read changes.
+    pub fn with_lenient_magic(mut self, lenient: bool) -> Self {
+        self.lenient_magic = lenient;
+        self
+    }
+
+    /// Read this archive with `format`'s layout if it starts with `magic`.
+    ///
+    /// Call this once per variant the archive may be. `read_kind` accepts
+    /// `magic` (as `Kind::Other`, unless it is `BIG4` or `BIGF`) without
+    /// `with_lenient_magic`.
+    ///
+    /// Archives in a format other than `Format::STANDARD` can be read but not
+    /// written, so `packer::repack` must be given a `kind` to convert them to.
+    pub fn with_format(mut self, magic: [u8; 4], format: Format) -> Self {
+        self.formats.retain(|&(m, _)| m != magic);
+        self.formats.push((magic, format));
+        self.table = Arc::default();
+        self
+    }
+
+    /// The layout used to read this archive, selected by its magic.
+    ///
+    /// This is `Format::STANDARD` unless a different one was given for this
+    /// archive's magic with `with_format`.
+    pub fn format(&self) -> Format {
+        let bytes = self.as_slice();
+        if bytes.len() < 4 {
+            return Format::STANDARD;
+        }
+
+        self.formats.iter()
+            .find(|&&(magic, _)| magic[..] == bytes[..4])
+            .map(|&(_, format)| format)
+            .unwrap_or(Format::STANDARD)
+    }
+
+    fn read_field(&self, field: HeaderField) -> Result<u32> {
+        let start = field.offset as usize;
+        let end = start + 4;
+        check_incomplete!(self, start, end);
+        Ok(field.endianness.read_u32(&self[start..end]))
+    }
+
+    /// Another `Archive` sharing this one's memory map, settings, and cached table.
+    ///
+    /// This is cheap: nothing is copied or re-read.
+    pub fn clone_handle(&self) -> Archive {
+        Archive {
+            data: self.data.clone(),
+            limits: self.limits,
+            lenient_magic: self.lenient_magic,
+            formats: self.formats.clone(),
+            table: self.table.clone(),
+        }
+    }
+
+    pub fn limits(&self) -> Limits {
+        self.limits
+    }
+
+    /// Check a table read from this archive for problems that do not prevent
+    /// reading but will produce wrong results when extracting, such as a bogus
+    /// declared size or entries whose data overlaps.
+    ///
+    /// Overlapping entries are a tell-tale sign of a hand-edited archive.
+    ///
+    /// Problems are collected into the returned report rather than returned as errors.
+    ///
+    /// This is `validate_with(table, Level::Structure)`.
+    pub fn validate(&self, table: &EntryInfoTable) -> Result<ValidationReport> {
+        self.validate_with(table, Level::Structure)
+    }
+
+    /// Like `validate` but with `Level::WithChecksums` also compare every entry
+    /// against the checksums stored by `packer::Settings::store_checksums`.
+    pub fn validate_with(&self, table: &EntryInfoTable, level: Level) -> Result<ValidationReport> {
+        let mut report = ValidationReport::default();
+        let actual = self.as_slice().len();
+
+        let declared = self.read_size()?;
+        if declared as usize != actual {
+            report.issues.push(Issue::SizeMismatch { declared, actual });
+        }
+
+        // Zero-length entries have no data to be out of bounds.
+        let mut out_of_bounds = table.values()
+            .filter(|e| e.len > 0 && e.offset as usize + e.len as usize > actual)
+            .collect::<Vec<_>>();
+
+        out_of_bounds.sort_by_key(|e| e.offset);
+
+        report.issues.extend(out_of_bounds.into_iter().map(|e| Issue::EntryOutOfBounds {
+            name: e.name.clone(),
+            start: e.offset as usize,
+            end: e.offset as usize + e.len as usize,
+        }));
+
+        let data_start = self.read_data_start()? as usize;
+        report.issues.extend(validation::find_overlaps(table, data_start));
+
+        let mut markers_with_data = table.values()
+            .filter(|e| e.is_directory_marker() && e.len > 0)
+            .collect::<Vec<_>>();
+
+        markers_with_data.sort_by_key(|e| e.index);
+
+        report.issues.extend(markers_with_data.into_iter().map(|e| Issue::DirectoryMarkerWithData {
+            name: e.name.clone(),
+            len: e.len,
+        }));
+
+        if level == Level::WithChecksums {
+            let table_end = self.secret_data_offset(table);
+            if table_end > data_start {
+                report.issues.push(Issue::TableEndsAfterDataStart { table_end, data_start });
+            }
+
+            let checksums = self.read_secret_data(table)?
+                .and_then(Checksums::from_secret_data);
+
+            match checksums {
+                Some(checksums) => report.issues.extend(validation::verify_checksums(table, &checksums, self.as_slice())),
+                None => report.issues.push(Issue::NoChecksums),
+            }
+        }
+
+        Ok(report)
+    }
+
+    /// The byte ranges after `data_start` that are not part of any entry in
+    /// `table` (see `validation::find_gaps`).
+    ///
+    /// Archives edited in place by other tools often contain several percent
+    /// of such slack. `packer::RepackSettings::compact` removes it.
+    pub fn find_gaps(&self, table: &EntryInfoTable) -> Result<Vec<Gap>> {
+        let data_start = self.read_data_start()? as usize;
+        Ok(validation::find_gaps(table, data_start, self.as_slice().len()))
+    }
+
+    /// Index the entries of `table` by `hash` of their names, for engines
+    /// that refer to entries by a CRC of their name instead of the name.
+    pub fn build_hash_index(&self, hash: HashFn) -> Result<HashIndex<'_>> {
+        Ok(HashIndex::new(self.table()?, hash))
     }
 
     /// The file signature that indicates whether or not
     /// this is a BIG archive.
     ///
     /// Little-endian ASCII sequence from offset 0 to 4 (high exclusive).
+    ///
+    /// See `with_lenient_magic` for archives with unrecognized magic.
     pub fn read_kind(&self) -> Result<Kind> {
         let start = 0;
         let end = 4;
         check_incomplete!(self, start, end);
-        Kind::try_from_bytes(&self[start..end])
+
+        let has_format = self.formats.iter().any(|&(magic, _)| magic[..] == self[start..end]);
+        if self.lenient_magic || has_format {
+            Kind::from_bytes_lenient(&self[start..end])
+        } else {
+            Kind::try_from_bytes(&self[start..end])
+        }
     }
 
     /// This is the size, in bytes, of the entire archive.
     ///
-    /// Little-endian u32 from offset 4 to 8 (high exclusive).
+    /// Little-endian u32 from offset 4 to 8 (high exclusive) in `Format::STANDARD`.
     pub fn read_size(&self) -> Result<u32> {
-        let start = 4;
-        let end = 8;
-        check_incomplete!(self, start, end);
-        let mut values = &self[start..end];
-        Ok(values.read_u32::<LittleEndian>()?)
+        self.read_field(self.format().size)
     }
 
     /// This is the number of entries stored in the archive.
     ///
-    /// Big-endian u32 from offset 8 to 12 (high exclusive).
+    /// Big-endian u32 from offset 8 to 12 (high exclusive) in `Format::STANDARD`.
     pub fn read_len(&self) -> Result<u32> {
-        let start = 8;
-        let end = 12;
-        check_incomplete!(self, start, end);
-        let mut values = &self[start..end];
-        Ok(values.read_u32::<BigEndian>()?)
+        self.read_field(self.format().len)
     }
 
     /// Offset at which the first entry's data starts.
     ///
-    /// Big-endian u32 from offset 12 to 16 (high exclusive).
+    /// Big-endian u32 from offset 12 to 16 (high exclusive) in `Format::STANDARD`.
     pub fn read_data_start(&self) -> Result<u32> {
-        let start = 12;
-        let end = 16;
-        check_incomplete!(self, start, end);
-        let mut values = &self[start..end];
-        Ok(values.read_u32::<BigEndian>()?)
+        self.read_field(self.format().data_start)
+    }
+
+    /// Read every field of the header at once.
+    pub fn read_header(&self) -> Result<Header> {
+        Ok(Header {
+            kind: self.read_kind()?,
+            size: self.read_size()?,
+            len: self.read_len()?,
+            data_start: self.read_data_start()?,
+        })
     }
 
     /// There is potentially a gap between the end of the header
@@ -171,17 +512,13 @@ impl Archive {
     ///
     /// I do not know if this needs to be aligned to a particular
     /// size for other BIG-manipulating tools to read it.
-    pub fn read_secret_data(&mut self, table: &EntryInfoTable) -> Result<Option<&[u8]>> {
-        let table_size = table.iter().map(|(_k, e)|
-            (std::mem::size_of::<u32>() + // offset
-             std::mem::size_of::<u32>() + // length
-             e.name.len() + 1) as u32 // name + null
-        ).sum::<u32>();
-
+    ///
+    /// Archives whose table ends at or after `data_start` (such as those
+    /// written by FinalBig, see `read_entry_metadata_table`) have none.
+    pub fn read_secret_data(&self, table: &EntryInfoTable) -> Result<Option<&[u8]>> {
+        let secret_data_offset = self.secret_data_offset(table);
         let data_start = self.read_data_start()? as usize;
-
-        let secret_data_offset = (Self::HEADER_LEN + table_size) as usize;
-        if secret_data_offset == data_start {
+        if secret_data_offset >= data_start {
             return Ok(None);
         }
 
@@ -189,58 +526,196 @@ impl Archive {
         Ok(Some(&self[secret_data_offset..data_start]))
     }
 
+    /// Where the records of `table` end in this archive.
+    fn secret_data_offset(&self, table: &EntryInfoTable) -> usize {
+        if let Some(table_end) = table.table_end() {
+            return table_end;
+        }
+
+        // Every record counts, including those shadowed by a later duplicate name.
+        let table_size = table.records().iter().map(|e|
+            std::mem::size_of::<u32>() + // offset
+            std::mem::size_of::<u32>() + // length
+            e.name.len() + 1 // name + null
+        ).sum::<usize>();
+
+        self.format().header_len as usize + table_size
+    }
+
     /// Read the metadata table that lists the entries in this archive.
     /// You will need to pass the resulting table to `get_data_from_table`
     /// to retrieve actual entry data.
-    pub fn read_entry_metadata_table(&mut self) -> Result<EntryInfoTable> {
-        // TODO: Do not trust `len`.
-        let len = self.read_len()?;
-
-        let mut c = io::Cursor::new(&self[..]);
-        c.seek(SeekFrom::Start(u64::from(Self::HEADER_LEN)))?;
-
-        let mut table = EntryInfoTable::new();
-
-        for _ in 0..len {
-            let offset = c.read_u32::<BigEndian>()?;
-            let len = c.read_u32::<BigEndian>()?;
-            let name = {
-                let mut buf = Vec::new();
-                let name_len = c.read_until(b'\0', &mut buf)?;
-                let name_cow = String::from_utf8_lossy(&buf[..name_len-1]);
-                name_cow.to_string()
+    ///
+    /// The table is expected to lie between the end of the header and
+    /// `data_start`. The header's `len` is not trusted: every record must
+    /// fit inside of that region.
+    ///
+    /// # Errors
+    ///
+    /// * If `data_start` lies beyond the end of the archive this will return
+    ///   `Err(Error::IncompleteArchive)`
+    /// * If `len` cannot possibly fit or a record crosses `data_start` this will
+    ///   return `Err(Error::CorruptTable)` identifying the offending record
+    /// * If a record's name is not null-terminated this will return `Err(Error::NameDecoding)`
+    /// * If the table exceeds this archive's `Limits` this will return
+    ///   `Err(Error::TooManyEntries)`, `Err(Error::EntryTooLarge)`, or `Err(Error::TotalSizeTooLarge)`
+    pub fn read_entry_metadata_table(&self) -> Result<EntryInfoTable> {
+        let format = self.format();
+        let len = self.read_len()? as usize;
+        let data_start = self.read_data_start()? as usize;
+        let header_len = format.header_len as usize;
+
+        if data_start < header_len {
+            return Err(Error::CorruptTable {
+                index: 0,
+                offset: data_start,
+                reason: format!("data start {:#X} lies inside of the {} byte header", data_start, header_len),
+            });
+        }
+
+        check_incomplete!(self, header_len, data_start);
+        self.limits.check_entry_count(len)?;
+
+        // NOTE: FinalBig writes a `data_start` 1 byte less than the end of the
+        // table so the final null terminator may sit exactly at `data_start`.
+        let bytes = self.as_slice();
+        let name_search_end = std::cmp::min(data_start + 1, bytes.len());
+
+        let max_records = (name_search_end - header_len) / Self::MIN_RECORD_LEN;
+        if len > max_records {
+            return Err(Error::CorruptTable {
+                index: max_records,
+                offset: header_len,
+                reason: format!("the header declares {} entries but the table can hold at most {}", len, max_records),
+            });
+        }
+
+        let mut entries = Vec::with_capacity(len);
+        let mut pos = header_len;
+        let mut total_size = 0u64;
+
+        for index in 0..len {
+            let record_end = pos + 2 * std::mem::size_of::<u32>();
+            if record_end > data_start {
+                return Err(Error::CorruptTable {
+                    index,
+                    offset: pos,
+                    reason: format!("the record extends past data start {:#X}", data_start),
+                });
+            }
+
+            let offset = format.record_endianness.read_u32(&bytes[pos..pos + 4]);
+            let len = format.record_endianness.read_u32(&bytes[pos + 4..record_end]);
+            pos = record_end;
+
+            let name = match bytes[pos..name_search_end].iter().position(|&b| b == b'\0') {
+                Some(name_len) => {
+                    let name = String::from_utf8_lossy(&bytes[pos..pos + name_len]).into_owned();
+                    pos += name_len + 1;
+                    name
+                },
+                None => return Err(Error::NameDecoding {
+                    index,
+                    offset: pos,
+                    reason: String::from("the name is not null-terminated"),
+                }),
             };
 
-            // TODO: Investigate K=&str so `clone()` can be avoided
-            table.insert(name.clone(), EntryInfo { offset, len, name });
+            self.limits.check_entry_size(&name, u64::from(len))?;
+            total_size += u64::from(len);
+            self.limits.check_total_size(total_size)?;
+
+            entries.push(EntryInfo { offset, len, name, index });
         }
 
+        let mut table = EntryInfoTable::from_entries(entries);
+        table.set_table_end(pos);
         Ok(table)
     }
 
+    /// This archive's table, read by `read_entry_metadata_table` the first time
+    /// it is needed and cached for the lifetime of the archive (and all of its
+    /// `clone_handle`s).
+    ///
+    /// Errors are not cached so a failed read will be attempted again.
+    pub fn table(&self) -> Result<&EntryInfoTable> {
+        if let Some(table) = self.table.get() {
+            return Ok(table);
+        }
+
+        // NOTE: Two threads may both read the table here; only one is kept.
+        let table = self.read_entry_metadata_table()?;
+        Ok(self.table.get_or_init(|| table))
+    }
+
     /// Given a table from this archive's `read_entry_metadata_table` and an
     /// entry name return the data of the named file if this archive
     /// contains a file by that name.
     ///
-    /// # Panics
-    /// If you provide this a table from a different archive that happens to
-    /// share an entry name with an entry in this archive this *may* panic.
+    /// # Errors
     ///
-    /// A panic will occurr if data start or end for an entry lies outside
-    /// of the archive file's boundaries.
-    pub fn get_bytes_via_table(&mut self, table: &EntryInfoTable, name: &str) -> Result<Option<&[u8]>> {
+    /// * If `table` does not contain `name` this will return `Err(Error::NoSuchEntry)`
+    /// * If the entry's data lies outside of this archive's boundaries (for example
+    ///   because `table` came from a different archive) this will return
+    ///   `Err(Error::EntryOutOfBounds)`
+    pub fn get_bytes_via_table(&self, table: &EntryInfoTable, name: &str) -> Result<Option<&[u8]>> {
         match table.get(name) {
-            Some(entry) => {
-                let start = entry.offset as usize;
-                let end = entry.offset as usize + entry.len as usize;
-                check_incomplete!(self, start, end);
-                let data = &self[start..end];
-                Ok(Some(data))
-            },
-            None => Err(Error::NoSuchEntry),
+            Some(entry) => self.get_bytes_via_entry(entry).map(Some),
+            None => Err(Error::NoSuchEntry { name: name.to_string() }),
         }
     }
 
+    /// Return the (raw, undecoded) data of an entry read from this archive's table.
+    ///
+    /// Zero-length entries are always empty: their offset is not checked as
+    /// some tools write `0` or leave garbage there.
+    ///
+    /// # Errors
+    ///
+    /// * If the entry's data lies outside of this archive's boundaries (for example
+    ///   because `entry` came from a different archive) this will return
+    ///   `Err(Error::EntryOutOfBounds)`
+    pub fn get_bytes_via_entry(&self, entry: &EntryInfo) -> Result<&[u8]> {
+        if entry.len == 0 {
+            return Ok(&[]);
+        }
+
+        let start = entry.offset as usize;
+        let end = entry.offset as usize + entry.len as usize;
+        let archive_len = self.as_slice().len();
+        if archive_len < end {
+            return Err(Error::EntryOutOfBounds {
+                name: entry.name.clone(),
+                start,
+                end,
+                archive_len,
+            });
+        }
+
+        Ok(&self[start..end])
+    }
+
+    /// Find the entry whose record is at position `index` in this archive's table.
+    ///
+    /// This is useful when names are garbled and entries are easier to address
+    /// positionally. Records hidden by a later record of the same name (see
+    /// `EntryInfoTable::shadowed`) are found too.
+    pub fn entry_by_index<'t>(&self, table: &'t EntryInfoTable, index: usize) -> Option<&'t EntryInfo> {
+        table.record(index)
+    }
+
+    /// Find the entry whose data contains the byte at `offset`.
+    ///
+    /// If no entry contains `offset` a zero-length entry located exactly
+    /// at `offset` is returned instead, if there is one.
+    pub fn entry_at_offset<'t>(&self, table: &'t EntryInfoTable, offset: u32) -> Option<&'t EntryInfo> {
+        let offset = u64::from(offset);
+
+        table.values()
+            .find(|e| u64::from(e.offset) <= offset && offset < u64::from(e.offset) + u64::from(e.len))
+            .or_else(|| table.values().find(|e| e.len == 0 && u64::from(e.offset) == offset))
+    }
+
     /// Get a slice of the binary data that makes up this archive (header, table, and file data).
     ///
     /// This is useful for writing in-memory archives to, for example, files.
@@ -249,6 +724,12 @@ impl Archive {
     }
 }
 
+impl PartialEq for Archive {
+    fn eq(&self, other: &Self) -> bool {
+        *self.data == *other.data && self.limits == other.limits
+    }
+}
+
 #[doc(hidden)]
 impl Deref for Archive {
     type Target = [u8];
@@ -262,6 +743,7 @@ impl Deref for Archive {
 mod tests {
     use super::*;
     use ::packer;
+    use ::testing::{self, header_bytes};
     use byteorder::LittleEndian;
 
     #[test]
@@ -296,9 +778,32 @@ mod tests {
         assert_matches!(Kind::try_from_bytes(&bytes), Err(Error::InvalidMagic { magic: ref b }) if *b == bytes);
     }
 
+    #[test]
+    fn kind_from_bytes_lenient() {
+        assert_matches!(Kind::from_bytes_lenient(b"BIGF"), Ok(Kind::BigF));
+        assert_matches!(Kind::from_bytes_lenient(b"BIGX"), Ok(Kind::Other(ref m)) if m == b"BIGX");
+        assert_matches!(Kind::from_bytes_lenient(b"BIG"), Err(Error::InvalidMagic { .. }));
+        assert_eq!(Kind::Other(*b"BIGX").magic(), *b"BIGX");
+        assert_eq!(Kind::Big4.magic(), *b"BIG4");
+    }
+
+    #[test]
+    fn archive_with_lenient_magic() {
+        let mut bytes = packer::pack(vec![("a", &b"a"[..])], Kind::BigF).unwrap().as_slice().to_vec();
+        bytes[..4].copy_from_slice(b"XBIG");
+
+        let archive = Archive::from_bytes(&bytes).unwrap();
+        assert_matches!(archive.read_kind(), Err(Error::InvalidMagic { .. }));
+
+        let archive = archive.with_lenient_magic(true);
+        assert_matches!(archive.read_header(), Ok(Header { kind: Kind::Other(ref m), len: 1, .. }) if m == b"XBIG");
+        assert_matches!(archive.clone_handle().read_kind(), Ok(Kind::Other(_)));
+        assert_eq!(archive.read_entry_metadata_table().unwrap().len(), 1);
+    }
+
     #[test]
     fn archive_from_bytes() {
-        let result = Archive::from_bytes(&vec![0]);
+        let result = Archive::from_bytes(&[0]);
         assert!(result.is_ok())
     }
 
@@ -380,7 +885,7 @@ mod tests {
     fn archive_read_size_u32_max() {
         use byteorder::WriteBytesExt;
 
-        let expected = ::std::u32::MAX;
+        let expected = u32::MAX;
 
         let mut bytes = b"BIGF".to_vec();
         bytes.write_u32::<LittleEndian>(expected).unwrap();
@@ -414,7 +919,7 @@ mod tests {
             (name2, &data2[..]),
         ];
 
-        let mut archive = packer::pack(entries, Kind::BigF).unwrap();
+        let archive = packer::pack(entries, Kind::BigF).unwrap();
         let table = archive.read_entry_metadata_table();
         assert!(table.is_ok());
         let table = table.unwrap();
@@ -424,6 +929,106 @@ mod tests {
         assert!(!table.contains_key("some/other/key.ini"));
     }
 
+    #[test]
+    fn archive_read_entry_metadata_table_unterminated_name() {
+        let mut bytes = header_bytes(27, 1, 27);
+        bytes.extend_from_slice(&[0, 0, 0, 27, 0, 0, 0, 0]);
+        bytes.extend_from_slice(b"abc");
+
+        let archive = Archive::from_bytes(&bytes).unwrap();
+        let res_table = archive.read_entry_metadata_table();
+        assert_matches!(res_table, Err(Error::NameDecoding { index: 0, offset: 24, .. }));
+    }
+
+    #[test]
+    fn archive_read_entry_metadata_table_absurd_len() {
+        let mut bytes = header_bytes(34, u32::MAX, 34);
+        bytes.extend_from_slice(&[0, 0, 0, 34, 0, 0, 0, 0, b'a', 0]);
+        bytes.extend_from_slice(&[0; 8]);
+
+        let archive = Archive::from_bytes(&bytes).unwrap();
+        let res_table = archive.read_entry_metadata_table();
+        assert_matches!(res_table, Err(Error::CorruptTable { index: 2, .. }));
+    }
+
+    #[test]
+    fn archive_read_entry_metadata_table_record_crosses_data_start() {
+        let mut bytes = header_bytes(50, 2, 34);
+        bytes.extend_from_slice(&[0, 0, 0, 34, 0, 0, 0, 0]);
+        bytes.extend_from_slice(b"abcdefgh\0");
+        bytes.extend_from_slice(&[0, 0, 0, 34, 0, 0, 0, 0, b'b', 0]);
+        bytes.extend_from_slice(&[0; 7]);
+
+        let archive = Archive::from_bytes(&bytes).unwrap();
+        let res_table = archive.read_entry_metadata_table();
+        assert_matches!(res_table, Err(Error::CorruptTable { index: 1, offset: 33, .. }));
+    }
+
+    #[test]
+    fn archive_read_entry_metadata_table_data_start_past_end() {
+        let mut bytes = header_bytes(26, 1, 0x1000);
+        bytes.extend_from_slice(&[0, 0, 0, 26, 0, 0, 0, 0, b'a', 0]);
+
+        let archive = Archive::from_bytes(&bytes).unwrap();
+        let res_table = archive.read_entry_metadata_table();
+        assert_matches!(res_table, Err(Error::IncompleteArchive { expected_len: 0x1000, .. }));
+    }
+
+    #[test]
+    fn archive_read_entry_metadata_table_limits() {
+        let archive = packer::pack(vec![("a", &[0; 8][..]), ("b", &[0; 8][..])], Kind::BigF).unwrap();
+
+        let limits = Limits { max_entries: Some(1), ..Limits::default() };
+        let archive = archive.with_limits(limits);
+        assert_matches!(archive.read_entry_metadata_table(), Err(Error::TooManyEntries { count: 2, max: 1 }));
+
+        let limits = Limits { max_entry_size: Some(7), ..Limits::default() };
+        let archive = archive.with_limits(limits);
+        assert_matches!(archive.read_entry_metadata_table(), Err(Error::EntryTooLarge { size: 8, .. }));
+
+        let limits = Limits { max_total_size: Some(15), ..Limits::default() };
+        let archive = archive.with_limits(limits);
+        assert_matches!(archive.read_entry_metadata_table(), Err(Error::TotalSizeTooLarge { size: 16, max: 15 }));
+
+        let limits = Limits { max_entries: Some(2), max_entry_size: Some(8), max_total_size: Some(16) };
+        let archive = archive.with_limits(limits);
+        assert!(archive.read_entry_metadata_table().is_ok());
+    }
+
+    #[test]
+    fn archive_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Archive>();
+    }
+
+    #[test]
+    fn archive_clone_handle_shares_table() {
+        let archive = packer::pack(vec![("a", &b"a"[..])], Kind::BigF).unwrap();
+        let handle = archive.clone_handle();
+
+        let table = archive.table().unwrap();
+        assert!(::std::ptr::eq(table, handle.table().unwrap()));
+        assert_eq!(*table, archive.read_entry_metadata_table().unwrap());
+        assert_eq!(archive, handle);
+    }
+
+    #[test]
+    fn archive_read_concurrently() {
+        let archive = packer::pack(vec![("a", &b"aaaa"[..]), ("b", &b"bbbb"[..])], Kind::BigF).unwrap();
+
+        let threads = (0..4).map(|_| {
+            let archive = archive.clone_handle();
+            ::std::thread::spawn(move || {
+                let table = archive.table().unwrap();
+                archive.get_bytes_via_table(table, "b").unwrap().unwrap().to_vec()
+            })
+        }).collect::<Vec<_>>();
+
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), b"bbbb");
+        }
+    }
+
     #[test]
     fn archive_get_bytes_via_table() {
         let name = "first/entry.txt";
@@ -431,7 +1036,7 @@ mod tests {
 
         let entries = vec![(name, &data[..])];
 
-        let mut archive = packer::pack(entries, Kind::BigF).unwrap();
+        let archive = packer::pack(entries, Kind::BigF).unwrap();
 
         let table = archive.read_entry_metadata_table();
         assert!(table.is_ok());
@@ -449,7 +1054,7 @@ mod tests {
 
         let entries = vec![(name, &data[..])];
 
-        let mut archive = packer::pack(entries, Kind::BigF).unwrap();
+        let archive = packer::pack(entries, Kind::BigF).unwrap();
 
         let table = archive.read_entry_metadata_table();
         assert!(table.is_ok());
@@ -459,4 +1064,261 @@ mod tests {
         let res_opt_bytes = archive.get_bytes_via_table(&table, name);
         assert_matches!(res_opt_bytes, Ok(Some(bytes)) if bytes == data);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn archive_validate_packed() {
+        let entries = vec![
+            ("first/entry.txt", &[0, 1, 2, 3][..]),
+            ("second/entry/bar.txt", &[0, 9, 8, 7][..]),
+        ];
+
+        let archive = packer::pack(entries, Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        let report = archive.validate(&table).unwrap();
+        assert!(report.is_valid());
+    }
+
+    #[test]
+    fn archive_validate_with_checksums() {
+        let archive = {
+            let mut builder = packer::ArchiveBuilder::new(Kind::BigF);
+            builder.add("a", b"aaaa").add("b", b"bbbb").checksums(true);
+            builder.build().unwrap()
+        };
+
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert!(archive.validate_with(&table, Level::WithChecksums).unwrap().is_valid());
+
+        let mut bytes = archive.as_slice().to_vec();
+        let b_offset = table["b"].offset as usize;
+        bytes[b_offset] = b'X';
+
+        let corrupt = Archive::from_bytes(&bytes).unwrap();
+        assert!(corrupt.validate(&table).unwrap().is_valid());
+        assert_matches!(corrupt.validate_with(&table, Level::WithChecksums).unwrap().issues.as_slice(),
+            [Issue::ChecksumMismatch { ref name, .. }] if name == "b");
+
+        let plain = packer::pack(vec![("a", &b"aaaa"[..])], Kind::BigF).unwrap();
+        let table = plain.read_entry_metadata_table().unwrap();
+        assert_eq!(plain.validate_with(&table, Level::WithChecksums).unwrap().issues, vec![Issue::NoChecksums]);
+
+        let final_big = Archive::from_bytes(&testing::final_big(&[("a", b"aaaa")])).unwrap();
+        let table = final_big.read_entry_metadata_table().unwrap();
+        assert!(final_big.validate(&table).unwrap().is_valid());
+        assert_eq!(final_big.validate_with(&table, Level::WithChecksums).unwrap().issues, vec![
+            Issue::TableEndsAfterDataStart { table_end: 26, data_start: 25 },
+            Issue::NoChecksums,
+        ]);
+    }
+
+    #[test]
+    fn archive_validate_mis_sized() {
+        let entries = [("a", &b"aaaa"[..])];
+        let actual = testing::valid(&entries).len();
+
+        let archive = Archive::from_bytes(&testing::mis_sized(&entries, 1000)).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert_eq!(archive.validate(&table).unwrap().issues, vec![Issue::SizeMismatch { declared: 1000, actual }]);
+    }
+
+    #[test]
+    fn archive_read_truncated() {
+        let entries = [("a", &b"aaaa"[..]), ("b", &b"bb"[..])];
+
+        let archive = Archive::from_bytes(&testing::truncated(&entries, 20)).unwrap();
+        assert!(archive.read_header().is_ok());
+        assert_matches!(archive.read_entry_metadata_table(), Err(Error::IncompleteArchive { actual_len: 20, .. }));
+
+        // The table is intact but the data of the last entry is cut short.
+        let len = testing::valid(&entries).len() - 1;
+        let archive = Archive::from_bytes(&testing::truncated(&entries, len)).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert_matches!(archive.get_bytes_via_table(&table, "b"), Err(Error::EntryOutOfBounds { .. }));
+    }
+
+    #[test]
+    fn archive_read_secret_data_final_big() {
+        let archive = Archive::from_bytes(&testing::final_big(&[("a", b"aaaa"), ("b", b"bb")])).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert_matches!(archive.read_secret_data(&table), Ok(None));
+        assert_matches!(archive.get_bytes_via_table(&table, "b"), Ok(Some(b)) if b == b"bb");
+
+        // Tables that were not read from the archive are measured from their records.
+        let table = EntryInfoTable::from_entries(table.values().cloned().collect());
+        assert_matches!(archive.read_secret_data(&table), Ok(None));
+    }
+
+    #[test]
+    fn archive_read_secret_data_duplicate_names() {
+        let archive = Archive::from_bytes(&testing::duplicate_names()).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert_eq!(table.len(), 2);
+        assert_matches!(archive.read_secret_data(&table), Ok(Some(s)) if s == b"secret");
+        assert_matches!(archive.get_bytes_via_table(&table, "a"), Ok(Some(b)) if b == b"a");
+
+        let table = EntryInfoTable::from_entries(table.into());
+        assert_matches!(archive.read_secret_data(&table), Ok(Some(s)) if s == b"secret");
+    }
+
+    #[test]
+    fn archive_validate_overlap() {
+        let archive = Archive::from_bytes(&testing::overlapping()).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        let report = archive.validate(&table).unwrap();
+        assert_eq!(report.issues, vec![
+            Issue::Overlap { first: "a".into(), second: "b".into(), start: 38, end: 40 },
+        ]);
+    }
+
+    #[test]
+    fn archive_with_format() {
+        use byteorder::{BigEndian, WriteBytesExt};
+
+        // A 20-byte header with an extra field at offset 8 and little-endian records.
+        let format = Format {
+            header_len: 20,
+            size: HeaderField::new(4, Endianness::Big),
+            len: HeaderField::new(12, Endianness::Little),
+            data_start: HeaderField::new(16, Endianness::Little),
+            record_endianness: Endianness::Little,
+        };
+
+        let mut bytes = b"BIGH".to_vec();
+        bytes.write_u32::<BigEndian>(45).unwrap();
+        bytes.write_u32::<BigEndian>(0xDEAD_BEEF).unwrap();
+        bytes.write_u32::<LittleEndian>(2).unwrap();
+        bytes.write_u32::<LittleEndian>(40).unwrap();
+        for &(offset, len, name) in &[(40, 3, "a"), (43, 2, "b")] {
+            bytes.write_u32::<LittleEndian>(offset).unwrap();
+            bytes.write_u32::<LittleEndian>(len).unwrap();
+            bytes.extend_from_slice(name.as_bytes());
+            bytes.push(0);
+        }
+        bytes.extend_from_slice(b"aaabb");
+
+        let archive = Archive::from_bytes(&bytes).unwrap();
+        assert_eq!(archive.format(), Format::STANDARD);
+        assert_matches!(archive.read_kind(), Err(Error::InvalidMagic { .. }));
+
+        let archive = archive.with_format(*b"BIGH", format);
+        assert_eq!(archive.format(), format);
+        assert_eq!(archive.read_header().unwrap(), Header { kind: Kind::Other(*b"BIGH"), size: 45, len: 2, data_start: 40 });
+
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert_matches!(archive.get_bytes_via_table(&table, "a"), Ok(Some(b)) if b == b"aaa");
+        assert_matches!(archive.get_bytes_via_table(&table, "b"), Ok(Some(b)) if b == b"bb");
+        assert_matches!(archive.read_secret_data(&table), Ok(None));
+        assert!(archive.validate(&table).unwrap().is_valid());
+
+        // Only the magic the format was given for is read with it.
+        let standard = Archive::from_bytes(&testing::valid(&[("a", b"a")])).unwrap()
+            .with_format(*b"BIGH", format);
+        assert_eq!(standard.format(), Format::STANDARD);
+
+        // Such archives can only be written after converting them.
+        let settings = packer::RepackSettings::default();
+        assert_matches!(packer::repack(&archive, &settings), Err(Error::UnsupportedFormat { .. }));
+
+        let settings = packer::RepackSettings { kind: Some(Kind::BigF), ..packer::RepackSettings::default() };
+        let converted = packer::repack(&archive, &settings).unwrap();
+        let table = converted.read_entry_metadata_table().unwrap();
+        assert_matches!(converted.get_bytes_via_table(&table, "b"), Ok(Some(b)) if b == b"bb");
+    }
+
+    #[test]
+    fn archive_zero_length_and_directory_marker_entries() {
+        // A zero-length entry with a garbage offset, and a directory marker with data.
+        let mut bytes = testing::header_bytes(45, 2, 43);
+        bytes.extend(testing::record_bytes(0xFFFF_FFF0, 0, "empty"));
+        bytes.extend(testing::record_bytes(43, 2, "Dir/"));
+        bytes.extend_from_slice(b"xx");
+
+        let archive = Archive::from_bytes(&bytes).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        assert_matches!(archive.get_bytes_via_table(&table, "empty"), Ok(Some(b)) if b.is_empty());
+        assert!(!table.get("empty").unwrap().is_directory_marker());
+        assert!(table.get("Dir/").unwrap().is_directory_marker());
+
+        let report = archive.validate(&table).unwrap();
+        assert_eq!(report.issues, vec![
+            Issue::DirectoryMarkerWithData { name: "Dir/".into(), len: 2 },
+        ]);
+    }
+
+    #[test]
+    fn archive_entry_by_index() {
+        let entries = vec![
+            ("b.txt", &[1, 2][..]),
+            ("a.txt", &[3][..]),
+        ];
+
+        let archive = packer::pack(entries, Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        assert_eq!(archive.entry_by_index(&table, 0).map(|e| e.name.as_str()), Some("b.txt"));
+        assert_eq!(archive.entry_by_index(&table, 1).map(|e| e.name.as_str()), Some("a.txt"));
+        assert!(archive.entry_by_index(&table, 2).is_none());
+
+        // The first "a" is shadowed by the second but can still be read by position.
+        let archive = Archive::from_bytes(&testing::duplicate_names()).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        let names = (0..4)
+            .map(|index| archive.entry_by_index(&table, index).map(|e| e.name.as_str()))
+            .collect::<Vec<_>>();
+        assert_eq!(names, vec![Some("a"), Some("b"), Some("a"), None]);
+
+        let shadowed = archive.entry_by_index(&table, 0).unwrap();
+        assert_eq!(archive.get_bytes_via_entry(shadowed).unwrap(), b"x");
+        assert_eq!(archive.get_bytes_via_entry(archive.entry_by_index(&table, 2).unwrap()).unwrap(), b"a");
+    }
+
+    #[test]
+    fn archive_entry_at_offset() {
+        let entries = vec![
+            ("b.txt", &[1, 2][..]),
+            ("empty.txt", &[][..]),
+            ("a.txt", &[3][..]),
+        ];
+
+        let archive = packer::pack(entries, Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        let start = archive.read_data_start().unwrap();
+
+        assert_eq!(archive.entry_at_offset(&table, start).map(|e| e.name.as_str()), Some("b.txt"));
+        assert_eq!(archive.entry_at_offset(&table, start + 1).map(|e| e.name.as_str()), Some("b.txt"));
+        assert_eq!(archive.entry_at_offset(&table, start + 2).map(|e| e.name.as_str()), Some("a.txt"));
+        assert!(archive.entry_at_offset(&table, start + 3).is_none());
+        assert!(archive.entry_at_offset(&table, 0).is_none());
+    }
+
+    #[test]
+    fn archive_peek() {
+        let archive = packer::pack(vec![("a", &b"aaa"[..]), ("b", &b"b"[..])], Kind::Big4).unwrap();
+        let bytes = archive.as_slice();
+
+        let header = Archive::peek(bytes).unwrap();
+        assert_eq!(header, archive.read_header().unwrap());
+        assert_eq!((header.kind, header.len, header.size as usize), (Kind::Big4, 2, bytes.len()));
+
+        // Only the header is needed.
+        assert_eq!(Archive::peek(&bytes[..16]).unwrap(), header);
+    }
+
+    #[test]
+    fn archive_peek_path() {
+        let archive = packer::pack(vec![("a", &b"a"[..])], Kind::BigF).unwrap();
+        let path = ::std::env::temp_dir().join(format!("easage-peek-{}.big", ::std::process::id()));
+        ::std::fs::write(&path, archive.as_slice()).unwrap();
+
+        assert_matches!(Archive::peek_path(&path), Ok(Header { kind: Kind::BigF, len: 1, .. }));
+
+        ::std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn archive_peek_incomplete() {
+        assert_matches!(Archive::peek(&b"BIGF\0\0"[..]), Err(Error::IncompleteArchive { actual_len: 6, expected_len: 16, .. }));
+        assert_matches!(Archive::peek(&b"NOPE------------"[..]), Err(Error::InvalidMagic { .. }));
+    }
+}
@@ -0,0 +1,280 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use walkdir::WalkDir;
+
+use ::{Archive, EntryInfo, EntryInfoTable, Result};
+
+/// One of the archives that make up an `ArchiveSet`.
+#[derive(Debug)]
+pub struct Member {
+    pub path: PathBuf,
+    pub archive: Archive,
+    pub table: EntryInfoTable,
+}
+
+/// An archive in an `ArchiveSet` that provides a given entry.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Provider<'a> {
+    /// Position of the providing archive in load order.
+    pub index: usize,
+    pub path: &'a Path,
+    pub entry: &'a EntryInfo,
+}
+
+/// An entry name that is provided by more than one archive.
+///
+/// `providers` is in load order so the first provider is the one that wins.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict<'a> {
+    pub name: &'a str,
+    pub providers: Vec<Provider<'a>>,
+}
+
+impl<'a> Conflict<'a> {
+    pub fn winner(&self) -> &Provider<'a> {
+        &self.providers[0]
+    }
+}
+
+/// Several archives that are resolved together the way the SAGE engine does.
+///
+/// The engine loads every `.big` in a directory in alphabetical order and
+/// the *first* archive to provide a given entry wins. This is why mods are
+/// commonly named with a leading `!`.
+///
+/// Entry names are matched case-insensitively and `/` is treated as `\`,
+/// again mirroring the engine.
+#[derive(Debug)]
+pub struct ArchiveSet {
+    members: Vec<Member>,
+    /// Normalized name to `(member index, entry name)` in load order.
+    index: HashMap<String, Vec<(usize, String)>>,
+}
+
+impl ArchiveSet {
+    /// Build a set from already-opened archives.
+    ///
+    /// `archives` must already be in load order (highest priority first).
+    pub fn new(archives: Vec<(PathBuf, Archive)>) -> Result<ArchiveSet> {
+        let mut members = Vec::with_capacity(archives.len());
+        let mut index = HashMap::<String, Vec<(usize, String)>>::new();
+
+        for (member_idx, (path, archive)) in archives.into_iter().enumerate() {
+            let table = archive.read_entry_metadata_table()?;
+
+            for name in table.keys() {
+                index.entry(normalize_name(name))
+                    .or_default()
+                    .push((member_idx, name.clone()));
+            }
+
+            members.push(Member { path, archive, table });
+        }
+
+        Ok(ArchiveSet { members, index })
+    }
+
+    /// Open every given path and build a set from them, sorted into load order.
+    pub fn from_paths<P: AsRef<Path>>(paths: &[P]) -> Result<ArchiveSet> {
+        let mut paths = paths.iter()
+            .map(|p| p.as_ref().to_path_buf())
+            .collect::<Vec<_>>();
+
+        sort_in_load_order(&mut paths);
+
+        let mut archives = Vec::with_capacity(paths.len());
+        for path in paths {
+            let archive = Archive::from_path(&path)?;
+            archives.push((path, archive));
+        }
+
+        ArchiveSet::new(archives)
+    }
+
+    /// Open every `.big` file directly inside of `directory` (like the engine
+    /// does for the game directory) and build a set from them.
+    pub fn from_directory<P: AsRef<Path>>(directory: P) -> Result<ArchiveSet> {
+        let paths = find_archives(directory, false)?;
+        ArchiveSet::from_paths(&paths)
+    }
+
+    /// The archives in this set in load order.
+    pub fn members(&self) -> &[Member] {
+        &self.members
+    }
+
+    /// Every distinct entry name in this set in its normalized form, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.index.keys().map(|name| name.as_str())
+    }
+
+    /// The archive whose copy of `name` the engine would use.
+    pub fn resolve(&self, name: &str) -> Option<Provider<'_>> {
+        self.providers(name).into_iter().next()
+    }
+
+    /// Every archive that contains `name`, in load order.
+    pub fn providers(&self, name: &str) -> Vec<Provider<'_>> {
+        match self.index.get(&normalize_name(name)) {
+            Some(sources) => self.providers_from(sources),
+            None => vec![],
+        }
+    }
+
+    /// Every entry name provided by more than one archive, sorted by name.
+    ///
+    /// Names are reported in their normalized (lowercase, `\`-separated) form.
+    pub fn conflicts(&self) -> Vec<Conflict<'_>> {
+        let mut conflicts = self.index.iter()
+            .filter(|&(_, sources)| sources.len() > 1)
+            .map(|(name, sources)| Conflict {
+                name: name.as_str(),
+                providers: self.providers_from(sources),
+            })
+            .collect::<Vec<_>>();
+
+        conflicts.sort_by(|a, b| a.name.cmp(b.name));
+        conflicts
+    }
+
+    /// The data of the winning copy of `name`, if any archive provides it.
+    pub fn get_bytes(&self, name: &str) -> Result<Option<&[u8]>> {
+        let (idx, entry_name) = match self.resolve(name) {
+            Some(provider) => (provider.index, provider.entry.name.clone()),
+            None => return Ok(None),
+        };
+
+        let member = &self.members[idx];
+        member.archive.get_bytes_via_table(&member.table, &entry_name)
+    }
+
+    fn providers_from(&self, sources: &[(usize, String)]) -> Vec<Provider<'_>> {
+        sources.iter()
+            .filter_map(|&(idx, ref entry_name)| {
+                let member = &self.members[idx];
+                member.table.get(entry_name).map(|entry| Provider {
+                    index: idx,
+                    path: &member.path,
+                    entry,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Sort archive paths into the order the engine loads them: case-insensitive alphabetical.
+pub fn sort_in_load_order(paths: &mut [PathBuf]) {
+    paths.sort_by_key(|p| p.to_string_lossy().to_lowercase());
+}
+
+/// Find every file with a `.big` extension (case-insensitive) in `directory`.
+///
+/// If `recursive` is `false` only the immediate children of `directory` are considered.
+pub fn find_archives<P: AsRef<Path>>(directory: P, recursive: bool) -> Result<Vec<PathBuf>> {
+    let mut walker = WalkDir::new(directory).min_depth(1);
+    if !recursive {
+        walker = walker.max_depth(1);
+    }
+
+    let mut paths = vec![];
+    for fs_item in walker {
+        let fs_item = fs_item?;
+        let path = fs_item.path();
+
+        let is_big = path.extension()
+            .map(|ext| ext.to_string_lossy().eq_ignore_ascii_case("big"))
+            .unwrap_or(false);
+
+        if is_big && fs_item.file_type().is_file() {
+            paths.push(path.to_path_buf());
+        }
+    }
+
+    sort_in_load_order(&mut paths);
+    Ok(paths)
+}
+
+/// Lowercase `name` and replace `/` with `\` so that it can be compared like the engine does.
+pub fn normalize_name(name: &str) -> String {
+    name.to_lowercase().replace('/', "\\")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::{packer, Kind};
+
+    fn set() -> ArchiveSet {
+        let base = packer::pack(vec![
+            ("Data\\INI\\Weapon.ini", &b"base weapon"[..]),
+            ("Data\\INI\\Armor.ini", &b"base armor"[..]),
+        ], Kind::BigF).unwrap();
+
+        let patch = packer::pack(vec![
+            ("data/ini/weapon.ini", &b"patched weapon"[..]),
+        ], Kind::BigF).unwrap();
+
+        ArchiveSet::new(vec![
+            (PathBuf::from("!patch.big"), patch),
+            (PathBuf::from("INI.big"), base),
+        ]).unwrap()
+    }
+
+    #[test]
+    fn archive_set_resolve() {
+        let set = set();
+
+        let winner = set.resolve("Data\\INI\\Weapon.ini").unwrap();
+        assert_eq!(winner.path, Path::new("!patch.big"));
+        assert_eq!(winner.entry.name, "data/ini/weapon.ini");
+
+        let winner = set.resolve("data/ini/armor.ini").unwrap();
+        assert_eq!(winner.path, Path::new("INI.big"));
+
+        assert!(set.resolve("Data\\INI\\Nope.ini").is_none());
+    }
+
+    #[test]
+    fn archive_set_providers() {
+        let set = set();
+        let providers = set.providers("DATA\\INI\\WEAPON.INI");
+
+        assert_eq!(providers.iter().map(|p| p.index).collect::<Vec<_>>(), vec![0, 1]);
+    }
+
+    #[test]
+    fn archive_set_conflicts() {
+        let set = set();
+        let conflicts = set.conflicts();
+
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].name, "data\\ini\\weapon.ini");
+        assert_eq!(conflicts[0].winner().path, Path::new("!patch.big"));
+    }
+
+    #[test]
+    fn archive_set_get_bytes() {
+        let set = set();
+
+        assert_matches!(set.get_bytes("Data\\INI\\Weapon.ini"), Ok(Some(b)) if b == b"patched weapon");
+        assert_matches!(set.get_bytes("Data\\INI\\Nope.ini"), Ok(None));
+    }
+
+    #[test]
+    fn archive_set_sort_in_load_order() {
+        let mut paths = vec![
+            PathBuf::from("W3DZH.big"),
+            PathBuf::from("!mod.big"),
+            PathBuf::from("audioZH.big"),
+        ];
+
+        sort_in_load_order(&mut paths);
+
+        assert_eq!(paths, vec![
+            PathBuf::from("!mod.big"),
+            PathBuf::from("audioZH.big"),
+            PathBuf::from("W3DZH.big"),
+        ]);
+    }
+}
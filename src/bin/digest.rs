@@ -0,0 +1,58 @@
+use clap::{Arg, ArgMatches};
+use sha2::{Digest, Sha256};
+
+use ::std::io::{self, Write};
+use ::std::path::Path;
+
+pub const ARG_NAME_PRINT_DIGEST: &'static str = "print-digest";
+
+pub fn arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(ARG_NAME_PRINT_DIGEST)
+        .long(ARG_NAME_PRINT_DIGEST)
+        .help(::i18n::text("digest-print-digest-help"))
+}
+
+pub fn is_requested(args: &ArgMatches) -> bool {
+    args.is_present(ARG_NAME_PRINT_DIGEST)
+}
+
+/// Print `digest` for `path` such that `sha256sum --check` can verify it.
+pub fn print(digest: &[u8], path: &Path) {
+    let hex = digest.iter()
+        .map(|b| format!("{:02x}", b))
+        .collect::<String>();
+
+    println!("{}  {}", hex, path.display());
+}
+
+pub fn sha256(data: &[u8]) -> Vec<u8> {
+    Sha256::digest(data).to_vec()
+}
+
+/// Hashes everything written through it.
+pub struct DigestWriter<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> DigestWriter<W> {
+    pub fn new(inner: W) -> Self {
+        DigestWriter { inner, hasher: Sha256::new() }
+    }
+
+    pub fn finish(self) -> (W, Vec<u8>) {
+        (self.inner, self.hasher.finalize().to_vec())
+    }
+}
+
+impl<W: Write> Write for DigestWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.hasher.update(&buf[..written]);
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
@@ -1,12 +1,21 @@
+use std::fmt;
 use std::io;
+use std::path::Path;
 
 extern crate clap;
-use clap::{App, AppSettings};
+extern crate sha2;
+use clap::{App, AppSettings, Arg};
 
 extern crate easage as lib;
 
 #[macro_use] extern crate failure;
 
+#[macro_use]
+mod i18n;
+
+mod easage_bench;
+use easage_bench as bench;
+
 mod easage_unpack;
 use easage_unpack as unpack;
 
@@ -16,75 +25,234 @@ use easage_list as list;
 mod easage_pack;
 use easage_pack as pack;
 
+mod easage_compact;
+use easage_compact as compact;
+
 mod easage_completions;
 use easage_completions as completions;
 
+mod easage_which;
+use easage_which as which;
+
+mod easage_cat;
+use easage_cat as cat;
+
+mod easage_hexdump;
+use easage_hexdump as hexdump;
+
+mod easage_lint;
+use easage_lint as lint;
+
+mod easage_repack;
+use easage_repack as repack;
+
+mod digest;
+mod io_args;
+mod glob;
+mod output;
+
 const NAME: &'static str = env!("CARGO_PKG_NAME");
 
 #[derive(Debug, Fail)]
 pub enum CliError {
-    #[fail(display = "Failed to pack the given directory: {:?}", inner)]
     PackArchive {
         #[cause]
         inner: lib::Error,
     },
 
-    #[fail(display = "I/O error: {} for path {:?}", inner, path)]
+    Lib {
+        #[cause]
+        inner: lib::Error,
+    },
+
     IO {
         #[cause]
         inner: io::Error,
 
+        operation: String,
         path: String,
     },
 
-    #[fail(display = "{}", message)]
+    NotFound {
+        message: String,
+    },
+
+    CheckFailed {
+        message: String,
+    },
+
     Custom {
         message: String,
     },
 }
 
-impl From<lib::Error> for CliError {
-    fn from(e: lib::Error) -> Self {
-        CliError::Custom { message: format!("{}", e) }
+impl CliError {
+    /// The process exit code that should be used when a command fails with this error.
+    pub fn exit_code(&self) -> i32 {
+        match *self {
+            CliError::NotFound { .. } => output::EXIT_NOT_FOUND,
+            CliError::CheckFailed { .. } => output::EXIT_CHECK_FAILED,
+            _ => output::EXIT_ERROR,
+        }
     }
-}
 
-impl From<::std::io::Error> for CliError {
-    fn from(e: ::std::io::Error) -> Self {
+    /// Wrap an `io::Error` that occurred while attempting to `operation` the given `path`.
+    pub fn io<P: AsRef<Path>>(inner: io::Error, operation: &str, path: P) -> Self {
         CliError::IO {
-            inner: e,
-            path: "<unknown>".into(),
+            inner,
+            operation: operation.to_string(),
+            path: path.as_ref().display().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for CliError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            CliError::PackArchive { ref inner } => write!(f, "{}", tr!("error-pack", message = describe(inner))),
+            CliError::Lib { ref inner } => write!(f, "{}", describe(inner)),
+            CliError::IO { ref inner, ref operation, ref path } => {
+                write!(f, "{}", tr!(&format!("io-{}", operation), path = path, message = inner))
+            },
+            CliError::NotFound { ref message }
+            | CliError::CheckFailed { ref message }
+            | CliError::Custom { ref message } => write!(f, "{}", message),
         }
     }
 }
 
+/// The message for a library error in the selected language.
+pub fn describe(e: &lib::Error) -> String {
+    use lib::Error;
+
+    fn hex(n: usize) -> String {
+        format!("{:#X}", n)
+    }
+
+    match *e {
+        Error::PathNotFound { ref path } => tr!("lib-path-not-found", path = path),
+        Error::AttemptCreateEmpty => tr!("lib-create-empty"),
+        Error::IncompleteArchive { actual_len, expected_len, read_start, read_end } => tr!("lib-incomplete-archive",
+            actual_len = actual_len, expected_len = expected_len, start = hex(read_start), end = hex(read_end)),
+        Error::CorruptTable { index, offset, ref reason } => tr!("lib-corrupt-table",
+            index = index, offset = hex(offset), reason = reason),
+        Error::NoSuchEntry { ref name } => tr!("lib-no-such-entry", name = name),
+        Error::EntryOutOfBounds { ref name, start, end, archive_len } => tr!("lib-entry-out-of-bounds",
+            name = name, start = hex(start), end = hex(end), archive_len = archive_len),
+        Error::DestinationExists { ref path } => tr!("lib-destination-exists", path = path),
+        Error::UnsafeEntryName { ref name } => tr!("lib-unsafe-entry-name", name = name),
+        Error::DirectoryMarkerWithData { ref name, len } => tr!("lib-directory-marker-with-data", name = name, len = len),
+        Error::CaseCollision { ref first, ref second } => tr!("lib-case-collision", first = first, second = second),
+        Error::TransformFailed { ref inner, ref name } => tr!("lib-transform-failed", name = name, message = inner),
+        Error::DuplicateEntry { ref name } => tr!("lib-duplicate-entry", name = name),
+        Error::ArchiveTooLarge { size, max_size } => tr!("lib-archive-too-large", size = size, max = max_size),
+        Error::TooManyEntries { count, max } => tr!("lib-too-many-entries", count = count, max = max),
+        Error::EntryTooLarge { ref name, size, max } => tr!("lib-entry-too-large", name = name, size = size, max = max),
+        Error::TotalSizeTooLarge { size, max } => tr!("lib-total-size-too-large", size = size, max = max),
+        Error::NameDecoding { index, offset, ref reason } => tr!("lib-name-decoding",
+            index = index, offset = hex(offset), reason = reason),
+        Error::IO { ref inner, ref operation } => tr!("lib-io", operation = operation, message = inner),
+        Error::InvalidMagic { ref magic } => tr!("lib-invalid-magic", magic = format!("{:?}", magic)),
+        Error::UnsupportedFormat { ref magic } => tr!("lib-unsupported-format", magic = format!("{:?}", magic)),
+        Error::Custom { ref message } => message.clone(),
+    }
+}
+
+impl From<lib::Error> for CliError {
+    fn from(e: lib::Error) -> Self {
+        CliError::Lib { inner: e }
+    }
+}
+
 pub type CliResult<T> = Result<T, CliError>;
 
+pub fn path_exists_and_is_file(path: String) -> Result<(), String> {
+    let path = Path::new(&path);
+    let md = path.metadata()
+        .map_err(|_e| tr!("invalid-path-metadata"))?;
+
+    if md.is_file() {
+        Ok(())
+    } else {
+        Err(tr!("invalid-path-not-file"))
+    }
+}
+
+/// Parse a decimal or `0x`-prefixed hexadecimal `u32`.
+pub fn parse_number(v: &str) -> Result<u32, String> {
+    let parsed = if v.starts_with("0x") || v.starts_with("0X") {
+        u32::from_str_radix(&v[2..], 16)
+    } else {
+        v.parse()
+    };
+
+    parsed.map_err(|_| tr!("invalid-number", value = v))
+}
+
+/// Parse `BIGF`, `BIG4`, or any other 4 ASCII characters as custom magic.
+pub fn parse_kind(v: &str) -> Result<lib::Kind, String> {
+    if v.len() != 4 || !v.is_ascii() {
+        return Err(tr!("invalid-kind", value = v));
+    }
+
+    lib::Kind::from_bytes_lenient(v.as_bytes())
+        .map_err(|e| describe(&e))
+}
+
+/// Warn that an archive of `kind` will only be readable by tools that accept custom magic.
+pub fn warn_if_custom_kind(kind: lib::Kind) {
+    if let lib::Kind::Other(magic) = kind {
+        eprintln!("{}", tr!("warning-custom-magic", magic = format!("{:?}", String::from_utf8_lossy(&magic))));
+    }
+}
+
 fn build_cli<'a, 'b>() -> App<'a, 'b> {
     App::new(NAME)
         .version(env!("CARGO_PKG_VERSION"))
-        .about("Read, create, and unpack from BIG archives")
+        .about(i18n::text("app-about"))
         .author("Taryn Hill <taryn@phrohdoh.com>")
         .setting(AppSettings::SubcommandRequiredElseHelp)
+        .arg(Arg::with_name(i18n::ARG_NAME_LANG)
+                .long(i18n::ARG_NAME_LANG)
+                .value_name("language")
+                .takes_value(true)
+                .global(true)
+                .help(i18n::text("lang-help")))
+        .subcommand(bench::get_command())
+        .subcommand(cat::get_command())
+        .subcommand(compact::get_command())
         .subcommand(completions::get_command())
+        .subcommand(hexdump::get_command())
+        .subcommand(lint::get_command())
         .subcommand(list::get_command())
         .subcommand(pack::get_command())
+        .subcommand(repack::get_command())
         .subcommand(unpack::get_command())
+        .subcommand(which::get_command())
 }
 
 fn main() {
+    i18n::init(i18n::requested_locale().as_deref());
+
     let matches = build_cli().get_matches();
 
     let run_result = match matches.subcommand() {
+        (bench::COMMAND_NAME, Some(args)) => bench::run(args),
+        (cat::COMMAND_NAME, Some(args)) => cat::run(args),
+        (compact::COMMAND_NAME, Some(args)) => compact::run(args),
         (completions::COMMAND_NAME, Some(args)) => completions::run(args),
+        (hexdump::COMMAND_NAME, Some(args)) => hexdump::run(args),
+        (lint::COMMAND_NAME, Some(args)) => lint::run(args),
         (list::COMMAND_NAME, Some(args)) => list::run(args),
         (pack::COMMAND_NAME, Some(args)) => pack::run(args),
+        (repack::COMMAND_NAME, Some(args)) => repack::run(args),
         (unpack::COMMAND_NAME, Some(args)) => unpack::run(args),
+        (which::COMMAND_NAME, Some(args)) => which::run(args),
         _ => Ok(()),
     };
 
     if let Err(err) = run_result {
-        eprintln!("ERROR: {}", err);
-        std::process::exit(1);
+        eprintln!("{}", tr!("error", message = err));
+        std::process::exit(err.exit_code());
     }
 }
\ No newline at end of file
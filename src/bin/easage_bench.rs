@@ -0,0 +1,265 @@
+use clap::{Arg, ArgMatches, ArgGroup, App, SubCommand};
+
+use ::std::env;
+use ::std::fs;
+use ::std::io::Write;
+use ::std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use ::std::process;
+use ::std::thread;
+use ::std::time::{Duration, Instant};
+
+use ::lib::{Archive, IoOptions, packer};
+use ::lib::extract::{self, ExtractOptions, OnConflict};
+use ::output::{self, Output};
+use ::io_args;
+use ::{CliResult, CliError, path_exists_and_is_file};
+
+pub const COMMAND_NAME: &'static str = "bench";
+const ARG_NAME_SOURCE: &'static str = "source";
+const ARG_NAME_DIRECTORY: &'static str = "directory";
+const ARG_NAME_ITERATIONS: &'static str = "iterations";
+const ARG_NAME_SCRATCH: &'static str = "scratch";
+
+const DEFAULT_ITERATIONS: &'static str = "5";
+
+pub fn get_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about(::i18n::text("bench-about"))
+        .author("Taryn Hill <taryn@phrohdoh.com>")
+        .arg(Arg::with_name(ARG_NAME_SOURCE)
+                .long(ARG_NAME_SOURCE)
+                .value_name(ARG_NAME_SOURCE)
+                .takes_value(true)
+                .validator(path_exists_and_is_file)
+                .help(::i18n::text("bench-source-help")))
+        .arg(Arg::with_name(ARG_NAME_DIRECTORY)
+                .long(ARG_NAME_DIRECTORY)
+                .value_name(ARG_NAME_DIRECTORY)
+                .takes_value(true)
+                .help(::i18n::text("bench-directory-help")))
+        .arg(Arg::with_name(ARG_NAME_ITERATIONS)
+                .long(ARG_NAME_ITERATIONS)
+                .value_name("n")
+                .takes_value(true)
+                .validator(validate_iterations)
+                .help(::i18n::text("bench-iterations-help")))
+        .arg(Arg::with_name(ARG_NAME_SCRATCH)
+                .long(ARG_NAME_SCRATCH)
+                .value_name("dir")
+                .takes_value(true)
+                .help(::i18n::text("bench-scratch-help")))
+        .arg(io_args::buffer_size_arg())
+        .arg(io_args::sequential_hint_arg())
+        .arg(io_args::preallocate_arg())
+        .args(&output::args())
+        .group(ArgGroup::with_name("inputs")
+                .args(&[ARG_NAME_SOURCE, ARG_NAME_DIRECTORY])
+                .multiple(true)
+                .required(true))
+}
+
+/// The timings of one benchmark.
+struct Measurement {
+    /// The stable name used in structured output.
+    name: &'static str,
+
+    /// The message the name is printed as in text output.
+    label: &'static str,
+
+    /// The bytes processed by every run.
+    bytes: u64,
+
+    /// Sorted from fastest to slowest.
+    runs: Vec<Duration>,
+}
+
+impl Measurement {
+    fn min(&self) -> Duration {
+        self.runs[0]
+    }
+
+    fn median(&self) -> Duration {
+        self.runs[self.runs.len() / 2]
+    }
+
+    fn max(&self) -> Duration {
+        self.runs[self.runs.len() - 1]
+    }
+
+    /// Bytes per second over the median run.
+    fn throughput(&self) -> u64 {
+        let secs = self.median().as_secs_f64();
+        if secs > 0.0 {
+            (self.bytes as f64 / secs) as u64
+        } else {
+            0
+        }
+    }
+}
+
+pub fn run(args: &ArgMatches) -> CliResult<()> {
+    let iterations = args.value_of(ARG_NAME_ITERATIONS).unwrap_or(DEFAULT_ITERATIONS).parse().unwrap();
+    let io = io_args::from_args(args);
+    let output = Output::from_args(args);
+
+    let scratch = args.value_of(ARG_NAME_SCRATCH)
+        .map(PathBuf::from)
+        .unwrap_or_else(env::temp_dir)
+        .join(format!("easage-bench-{}", process::id()));
+
+    let result = run_all(args, iterations, io, &scratch);
+    let _ = fs::remove_dir_all(&scratch);
+    let measurements = result?;
+
+    if !output.is_text() {
+        let rows = measurements.iter()
+            .map(|m| vec![
+                m.name.into(),
+                (m.runs.len() as u64).into(),
+                m.bytes.into(),
+                (m.min().as_micros() as u64).into(),
+                (m.median().as_micros() as u64).into(),
+                (m.max().as_micros() as u64).into(),
+                m.throughput().into(),
+            ])
+            .collect::<Vec<_>>();
+
+        output.print_rows(&["benchmark", "runs", "bytes", "min_us", "median_us", "max_us", "bytes_per_sec"], &rows);
+        return Ok(());
+    }
+
+    print_report(&measurements, io);
+    Ok(())
+}
+
+fn run_all(args: &ArgMatches, iterations: usize, io: IoOptions, scratch: &Path) -> CliResult<Vec<Measurement>> {
+    fs::create_dir_all(scratch)
+        .map_err(|e| CliError::io(e, "create", scratch))?;
+
+    let mut measurements = vec![];
+
+    if let Some(source) = args.value_of(ARG_NAME_SOURCE) {
+        let archive = Archive::from_path(source)?
+            .with_lenient_magic(true);
+
+        measurements.push(bench_table_parse(&archive, iterations)?);
+        measurements.push(bench_extract(&archive, iterations, io, &scratch.join("extract"))?);
+    }
+
+    if let Some(directory) = args.value_of(ARG_NAME_DIRECTORY) {
+        measurements.push(bench_pack(Path::new(directory), iterations, io, &scratch.join("pack.big"))?);
+    }
+
+    Ok(measurements)
+}
+
+/// Time `f` `iterations` times.
+fn time<F>(iterations: usize, mut f: F) -> CliResult<Vec<Duration>>
+    where F: FnMut() -> CliResult<()> {
+    let mut runs = Vec::with_capacity(iterations);
+    for _ in 0..iterations {
+        let start = Instant::now();
+        f()?;
+        runs.push(start.elapsed());
+    }
+
+    runs.sort();
+    Ok(runs)
+}
+
+fn bench_table_parse(archive: &Archive, iterations: usize) -> CliResult<Measurement> {
+    let data_start = u64::from(archive.read_data_start()?);
+
+    let runs = time(iterations, || {
+        archive.read_entry_metadata_table()?;
+        Ok(())
+    })?;
+
+    Ok(Measurement { name: "table-parse", label: "bench-table-parse", bytes: data_start, runs })
+}
+
+fn bench_extract(archive: &Archive, iterations: usize, io: IoOptions, output_dir: &Path) -> CliResult<Measurement> {
+    let table = archive.read_entry_metadata_table()?;
+    let bytes = table.values().map(|entry| u64::from(entry.len)).sum();
+
+    let options = ExtractOptions {
+        on_conflict: OnConflict::Overwrite,
+        restore_metadata: false,
+        io,
+        ..ExtractOptions::default()
+    };
+
+    let runs = time(iterations, || {
+        extract::extract_all(archive, &table, output_dir, &options)?;
+        Ok(())
+    })?;
+
+    Ok(Measurement { name: "extract", label: "bench-extract", bytes, runs })
+}
+
+fn bench_pack(directory: &Path, iterations: usize, io: IoOptions, output: &Path) -> CliResult<Measurement> {
+    let settings = || packer::Settings {
+        strip_prefix: Some(format!("{}{}", directory.display(), MAIN_SEPARATOR)),
+        io,
+        ..packer::Settings::default()
+    };
+
+    let bytes = packer::estimate_directory(directory, &settings())?.data;
+
+    let runs = time(iterations, || {
+        let archive = packer::pack_directory(directory, settings())
+            .map_err(|e_lib| CliError::PackArchive { inner: e_lib })?;
+
+        let file = fs::File::create(output)
+            .map_err(|e| CliError::io(e, "create", output))?;
+
+        let data = archive.as_slice();
+        io.preallocate(&file, data.len() as u64)
+            .map_err(|e| CliError::io(e, "allocate", output))?;
+
+        let mut writer = io.buffered(file);
+        writer.write_all(data)
+            .and_then(|_| writer.flush())
+            .map_err(|e| CliError::io(e, "write", output))?;
+
+        Ok(())
+    })?;
+
+    Ok(Measurement { name: "pack", label: "bench-pack", bytes, runs })
+}
+
+fn print_report(measurements: &[Measurement], io: IoOptions) {
+    let cpus = thread::available_parallelism().map(|n| n.get()).unwrap_or(1);
+
+    println!("{}", tr!("bench-environment",
+        version = env!("CARGO_PKG_VERSION"),
+        os = env::consts::OS,
+        arch = env::consts::ARCH,
+        cpus = cpus));
+
+    println!("{}", tr!("bench-io",
+        buffer_size = io.buffer_size,
+        sequential = io.sequential,
+        preallocate = io.preallocate));
+
+    for m in measurements {
+        println!("{}", tr!("bench-result",
+            name = format!("{:<14}", ::i18n::text(m.label)),
+            runs = m.runs.len(),
+            min = format_duration(m.min()),
+            median = format_duration(m.median()),
+            max = format_duration(m.max()),
+            throughput = format!("{:.1}", m.throughput() as f64 / (1024.0 * 1024.0))));
+    }
+}
+
+fn format_duration(d: Duration) -> String {
+    format!("{:.3}ms", d.as_secs_f64() * 1000.0)
+}
+
+fn validate_iterations(v: String) -> Result<(), String> {
+    match v.parse::<usize>() {
+        Ok(n) if n > 0 => Ok(()),
+        _ => Err(tr!("invalid-positive", arg = ARG_NAME_ITERATIONS)),
+    }
+}
@@ -0,0 +1,72 @@
+use ::std::io::{self, Write};
+
+use clap::{Arg, ArgMatches, ArgGroup, App, SubCommand};
+
+use ::lib::Archive;
+use ::{CliResult, CliError, parse_number, path_exists_and_is_file};
+
+pub const COMMAND_NAME: &'static str = "cat";
+const ARG_NAME_SOURCE: &'static str = "source";
+const ARG_NAME_ENTRY: &'static str = "entry";
+const ARG_NAME_INDEX: &'static str = "index";
+const ARG_NAME_OFFSET: &'static str = "offset";
+
+pub fn get_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about(::i18n::text("cat-about"))
+        .author("Taryn Hill <taryn@phrohdoh.com>")
+        .arg(Arg::with_name(ARG_NAME_SOURCE)
+                .index(1)
+                .takes_value(true)
+                .required(true)
+                .validator(path_exists_and_is_file)
+                .help(::i18n::text("cat-source-help")))
+        .arg(Arg::with_name(ARG_NAME_ENTRY)
+                .long(ARG_NAME_ENTRY)
+                .value_name(ARG_NAME_ENTRY)
+                .takes_value(true)
+                .help(::i18n::text("cat-entry-help")))
+        .arg(Arg::with_name(ARG_NAME_INDEX)
+                .long(ARG_NAME_INDEX)
+                .value_name(ARG_NAME_INDEX)
+                .takes_value(true)
+                .validator(|v| parse_number(&v).map(|_| ()))
+                .help(::i18n::text("cat-index-help")))
+        .arg(Arg::with_name(ARG_NAME_OFFSET)
+                .long(ARG_NAME_OFFSET)
+                .value_name(ARG_NAME_OFFSET)
+                .takes_value(true)
+                .validator(|v| parse_number(&v).map(|_| ()))
+                .help(::i18n::text("cat-offset-help")))
+        .group(ArgGroup::with_name("to-select")
+                .args(&[ARG_NAME_ENTRY, ARG_NAME_INDEX, ARG_NAME_OFFSET])
+                .required(true))
+}
+
+pub fn run(args: &ArgMatches) -> CliResult<()> {
+    let source = args.value_of(ARG_NAME_SOURCE).unwrap();
+
+    let archive = Archive::from_path(source)?;
+    let table = archive.read_entry_metadata_table()?;
+
+    let entry = if let Some(name) = args.value_of(ARG_NAME_ENTRY) {
+        table.get(name)
+            .ok_or_else(|| CliError::NotFound { message: tr!("no-entry-named", name = name, path = source) })?
+    } else if let Some(index) = args.value_of(ARG_NAME_INDEX) {
+        let index = parse_number(index).unwrap() as usize;
+        archive.entry_by_index(&table, index)
+            .ok_or_else(|| CliError::NotFound { message: tr!("no-entry-at-index", index = index, path = source) })?
+    } else {
+        let offset = parse_number(args.value_of(ARG_NAME_OFFSET).unwrap()).unwrap();
+        archive.entry_at_offset(&table, offset)
+            .ok_or_else(|| CliError::NotFound { message: tr!("no-entry-at-offset", offset = format!("{:#x}", offset), path = source) })?
+    };
+
+    let data = archive.get_bytes_via_entry(entry)?;
+
+    let stdout = io::stdout();
+    stdout.lock().write_all(data)
+        .map_err(|e| CliError::io(e, "write", "<stdout>"))?;
+
+    Ok(())
+}
@@ -0,0 +1,101 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+
+use ::std::fs;
+use ::std::io;
+use ::std::path::Path;
+
+use ::lib::{Archive, packer};
+use ::lib::validation::Gap;
+use ::digest;
+use ::io_args;
+use ::repack;
+use ::{CliResult, CliError, path_exists_and_is_file};
+
+pub const COMMAND_NAME: &'static str = "compact";
+const ARG_NAME_SOURCE: &'static str = "source";
+const ARG_NAME_OUTPUT: &'static str = "output";
+const ARG_NAME_DRY_RUN: &'static str = "dry-run";
+
+pub fn get_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about(::i18n::text("compact-about"))
+        .author("Taryn Hill <taryn@phrohdoh.com>")
+        .arg(Arg::with_name(ARG_NAME_SOURCE)
+                .long(ARG_NAME_SOURCE)
+                .value_name(ARG_NAME_SOURCE)
+                .takes_value(true)
+                .required(true)
+                .validator(path_exists_and_is_file)
+                .help(::i18n::text("compact-source-help")))
+        .arg(Arg::with_name(ARG_NAME_OUTPUT)
+                .long(ARG_NAME_OUTPUT)
+                .value_name(ARG_NAME_OUTPUT)
+                .takes_value(true)
+                .help(::i18n::text("compact-output-help")))
+        .arg(Arg::with_name(ARG_NAME_DRY_RUN)
+                .long(ARG_NAME_DRY_RUN)
+                .help(::i18n::text("compact-dry-run-help")))
+        .arg(digest::arg()
+                .conflicts_with(ARG_NAME_DRY_RUN))
+        .arg(io_args::buffer_size_arg())
+        .arg(io_args::sequential_hint_arg())
+}
+
+pub fn run(args: &ArgMatches) -> CliResult<()> {
+    let source = args.value_of(ARG_NAME_SOURCE).unwrap();
+    let output = Path::new(args.value_of(ARG_NAME_OUTPUT).unwrap_or(source));
+    let settings = packer::RepackSettings {
+        io: io_args::from_args(args),
+        ..packer::RepackSettings::compact()
+    };
+
+    let archive = Archive::from_path(source)?
+        .with_lenient_magic(true);
+    let original_len = archive.as_slice().len() as u64;
+
+    if args.is_present(ARG_NAME_DRY_RUN) {
+        let gaps = archive.find_gaps(archive.table()?)?;
+        for gap in &gaps {
+            println!("  {}", tr!("compact-gap", start = format!("{:#X}", gap.start), end = format!("{:#X}", gap.end), len = gap.len()));
+        }
+
+        let slack = gaps.iter().map(Gap::len).sum::<usize>();
+        let compacted_len = packer::repack_to(&archive, &settings, &mut io::sink())?;
+
+        println!("{}", tr!("compact-plan",
+            path = source,
+            slack = slack,
+            gaps = gaps.len(),
+            reclaimed = original_len.saturating_sub(compacted_len),
+            before = original_len,
+            after = compacted_len));
+
+        return Ok(());
+    }
+
+    let temp_path = repack::temp_path(output);
+    let result = repack::write_to(&archive, &settings, &temp_path);
+    drop(archive);
+
+    let sha256 = result?;
+
+    if let Err(e) = fs::rename(&temp_path, output) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(CliError::io(e, "replace", output));
+    }
+
+    let compacted_len = fs::metadata(output)
+        .map_err(|e| CliError::io(e, "read", output))?
+        .len();
+
+    println!("{}", tr!("compact-reclaimed",
+        reclaimed = original_len.saturating_sub(compacted_len),
+        before = original_len,
+        after = compacted_len));
+
+    if digest::is_requested(args) {
+        digest::print(&sha256, output);
+    }
+
+    Ok(())
+}
@@ -1,23 +1,80 @@
 use clap::{Arg, ArgMatches, App, SubCommand};
+
+use ::lib::Archive;
 use ::CliResult;
 
 pub const COMMAND_NAME: &'static str = "completions";
 const ARG_NAME_SHELL: &'static str = "shell";
+const ARG_NAME_LIST_ENTRIES: &'static str = "list-entries";
+
+// NOTE: Appended to the generated bash script so that `--names` and `--entry`
+// complete entry names from the archive given by `--source` (or the first
+// argument ending in `.big`) via `easage completions --list-entries`.
+const BASH_ENTRY_COMPLETION: &'static str = r#"
+_easage_with_entries() {
+    local cur="${COMP_WORDS[COMP_CWORD]}"
+    local prev="${COMP_WORDS[COMP_CWORD-1]}"
+
+    if [[ "$prev" != "--names" && "$prev" != "--entry" ]]; then
+        _easage "$@"
+        return
+    fi
+
+    local archive="" i
+    for ((i = 1; i < COMP_CWORD; i++)); do
+        case "${COMP_WORDS[i]}" in
+            --source) archive="${COMP_WORDS[i+1]}" ;;
+            *.big|*.BIG) [[ -z "$archive" ]] && archive="${COMP_WORDS[i]}" ;;
+        esac
+    done
+
+    COMPREPLY=()
+    [[ -z "$archive" ]] && return
+
+    local name
+    while IFS= read -r name; do
+        [[ "$name" == "$cur"* ]] && COMPREPLY+=("$(printf '%q' "$name")")
+    done < <(easage completions --list-entries "$archive" 2>/dev/null)
+}
+
+complete -F _easage_with_entries -o bashdefault -o default easage
+"#;
 
 pub fn get_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name(COMMAND_NAME)
-        .about("Generate tab-completion scripts (prints to stdout)")
+        .about(::i18n::text("completions-about"))
         .author("Taryn Hill <taryn@phrohdoh.com>")
         .arg(Arg::with_name(ARG_NAME_SHELL)
-                .required(true)
+                .required_unless(ARG_NAME_LIST_ENTRIES)
                 .possible_values(&["bash", "fish", "powershell", "zsh"])
-                .help("the shell to generate the script for"))
+                .help(::i18n::text("completions-shell-help")))
+        .arg(Arg::with_name(ARG_NAME_LIST_ENTRIES)
+                .long(ARG_NAME_LIST_ENTRIES)
+                .value_name("archive")
+                .takes_value(true)
+                .conflicts_with(ARG_NAME_SHELL)
+                .help(::i18n::text("completions-list-entries-help")))
 }
 
 pub fn run(args: &ArgMatches) -> CliResult<()> {
+    if let Some(path) = args.value_of(ARG_NAME_LIST_ENTRIES) {
+        let archive = Archive::from_path(path)?;
+        for name in archive.table()?.keys() {
+            println!("{}", name);
+        }
+
+        return Ok(());
+    }
+
     let shell = args.value_of(ARG_NAME_SHELL).unwrap();
+    let is_bash = shell == "bash";
     let shell = shell.parse().unwrap();
 
     ::build_cli().gen_completions_to(::NAME, shell, &mut ::std::io::stdout());
+
+    if is_bash {
+        print!("{}", BASH_ENTRY_COMPLETION);
+    }
+
     Ok(())
-}
\ No newline at end of file
+}
@@ -0,0 +1,112 @@
+use ::std::io::{self, BufWriter, Write};
+
+use clap::{Arg, ArgMatches, App, SubCommand};
+
+use ::lib::Archive;
+use ::{CliResult, CliError, parse_number, path_exists_and_is_file};
+
+pub const COMMAND_NAME: &'static str = "hexdump";
+const ARG_NAME_SOURCE: &'static str = "source";
+const ARG_NAME_ENTRY: &'static str = "entry";
+const ARG_NAME_RANGE: &'static str = "range";
+
+const BYTES_PER_LINE: usize = 16;
+
+pub fn get_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about(::i18n::text("hexdump-about"))
+        .author("Taryn Hill <taryn@phrohdoh.com>")
+        .arg(Arg::with_name(ARG_NAME_SOURCE)
+                .index(1)
+                .takes_value(true)
+                .required(true)
+                .validator(path_exists_and_is_file)
+                .help(::i18n::text("hexdump-source-help")))
+        .arg(Arg::with_name(ARG_NAME_ENTRY)
+                .long(ARG_NAME_ENTRY)
+                .value_name(ARG_NAME_ENTRY)
+                .takes_value(true)
+                .conflicts_with(ARG_NAME_RANGE)
+                .help(::i18n::text("hexdump-entry-help")))
+        .arg(Arg::with_name(ARG_NAME_RANGE)
+                .long(ARG_NAME_RANGE)
+                .value_name("START..END")
+                .takes_value(true)
+                .validator(|v| parse_range(&v).map(|_| ()))
+                .help(::i18n::text("hexdump-range-help")))
+}
+
+pub fn run(args: &ArgMatches) -> CliResult<()> {
+    let source = args.value_of(ARG_NAME_SOURCE).unwrap();
+    let archive = Archive::from_path(source)?;
+
+    let (start, end) = if let Some(name) = args.value_of(ARG_NAME_ENTRY) {
+        let table = archive.read_entry_metadata_table()?;
+        let entry = table.get(name)
+            .ok_or_else(|| CliError::NotFound { message: tr!("no-entry-named", name = name, path = source) })?;
+
+        let start = entry.offset as usize;
+        (start, start + entry.len as usize)
+    } else if let Some(range) = args.value_of(ARG_NAME_RANGE) {
+        let (start, end) = parse_range(range).unwrap();
+        (start as usize, end as usize)
+    } else {
+        (0, archive.as_slice().len())
+    };
+
+    let data = archive.as_slice();
+    if end > data.len() {
+        return Err(CliError::Custom {
+            message: tr!("hexdump-range-past-end", start = format!("{:#x}", start), end = format!("{:#x}", end), len = data.len()),
+        });
+    }
+
+    let stdout = io::stdout();
+    let mut out = BufWriter::new(stdout.lock());
+    write_dump(&mut out, &data[start..end], start)
+        .map_err(|e| CliError::io(e, "write", "<stdout>"))?;
+
+    Ok(())
+}
+
+/// Write `data` as lines of `offset  hex bytes  |ascii|` where offsets start at `base_offset`.
+fn write_dump<W: Write>(out: &mut W, data: &[u8], base_offset: usize) -> io::Result<()> {
+    for (line_idx, chunk) in data.chunks(BYTES_PER_LINE).enumerate() {
+        write!(out, "{:08x} ", base_offset + line_idx * BYTES_PER_LINE)?;
+
+        for idx in 0..BYTES_PER_LINE {
+            if idx % 8 == 0 {
+                write!(out, " ")?;
+            }
+
+            match chunk.get(idx) {
+                Some(b) => write!(out, "{:02x} ", b)?,
+                None => write!(out, "   ")?,
+            }
+        }
+
+        let ascii = chunk.iter()
+            .map(|&b| if b.is_ascii_graphic() || b == b' ' { b as char } else { '.' })
+            .collect::<String>();
+
+        writeln!(out, " |{}|", ascii)?;
+    }
+
+    out.flush()
+}
+
+fn parse_range(v: &str) -> Result<(u32, u32), String> {
+    let mut parts = v.splitn(2, "..");
+    let start = parts.next().unwrap_or("");
+    let end = parts.next()
+        .ok_or_else(|| tr!("invalid-range", value = v))?;
+
+    let start = parse_number(start)?;
+    let end = parse_number(end)?;
+
+    if start > end {
+        return Err(tr!("invalid-range-order", start = format!("{:#x}", start), end = format!("{:#x}", end)));
+    }
+
+    Ok((start, end))
+}
@@ -0,0 +1,62 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+
+use ::lib::Archive;
+use ::lib::lint;
+use ::output::{self, Output};
+use ::{CliResult, CliError, path_exists_and_is_file};
+
+pub const COMMAND_NAME: &'static str = "lint";
+const ARG_NAME_SOURCE: &'static str = "source";
+
+pub fn get_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about(::i18n::text("lint-about"))
+        .author("Taryn Hill <taryn@phrohdoh.com>")
+        .arg(Arg::with_name(ARG_NAME_SOURCE)
+                .index(1)
+                .takes_value(true)
+                .required(true)
+                .validator(path_exists_and_is_file)
+                .help(::i18n::text("lint-source-help")))
+        .args(&output::args())
+}
+
+pub fn run(args: &ArgMatches) -> CliResult<()> {
+    let path = args.value_of(ARG_NAME_SOURCE).unwrap();
+    let output = Output::from_args(args);
+
+    let archive = Archive::from_path(path)?;
+    let table = archive.read_entry_metadata_table()?;
+    let issues = lint::lint_names(&table);
+
+    if output.is_text() {
+        for issue in &issues {
+            println!("{}: {}", issue.code(), describe(issue));
+        }
+    } else {
+        let rows = issues.iter()
+            .map(|issue| vec![issue.code().into(), issue.names().into_iter().map(String::from).collect::<Vec<_>>().into(), describe(issue).into()])
+            .collect::<Vec<_>>();
+
+        output.print_rows(&["issue", "names", "description"], &rows);
+    }
+
+    if issues.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::CheckFailed {
+            message: tr!("lint-found", count = issues.len(), path = path),
+        })
+    }
+}
+
+/// The message for `issue` in the selected language.
+fn describe(issue: &lint::NameIssue) -> String {
+    let names = issue.names().join(", ");
+    let count = match *issue {
+        lint::NameIssue::Duplicate { count, .. } => count,
+        _ => issue.names().len(),
+    };
+
+    tr!(&format!("lint-{}", issue.code()), names = names, count = count)
+}
@@ -1,93 +1,97 @@
-use ::std::path::Path;
-
 use clap::{Arg, ArgMatches, App, SubCommand};
 
-use ::lib::{Archive, Error};
-use ::CliResult;
+use ::lib::Archive;
+use ::lib::archive_set::ArchiveSet;
+use ::output::{self, Output};
+use ::{CliResult, path_exists_and_is_file};
 
 pub const COMMAND_NAME: &'static str = "list";
 const ARG_NAME: &'static str = "source";
 const ARG_NAME_VERBOSE: &'static str = "verbose";
-
-fn path_exists_and_is_file(path: String) -> Result<(), String> {
-    let path = Path::new(&path);
-    let md = path.metadata()
-        .map_err(|_e| String::from("Unable to read metadata to validate path. Are you sure this file exists?"))?;
-
-    if md.is_file() {
-        Ok(())
-    } else {
-        Err(String::from("path must be an existing file (not a directory)"))
-    }
-}
+const ARG_NAME_LENIENT_MAGIC: &'static str = "lenient-magic";
+const ARG_NAME_DUPLICATES_ACROSS: &'static str = "duplicates-across";
 
 pub fn get_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name(COMMAND_NAME)
-        .about("List the contents of a BIG archive")
+        .about(::i18n::text("list-about"))
         .author("Taryn Hill <taryn@phrohdoh.com>")
         .arg(Arg::with_name(ARG_NAME)
                 .index(1)
                 .takes_value(true)
-                .required(true)
+                .required_unless(ARG_NAME_DUPLICATES_ACROSS)
+                .conflicts_with(ARG_NAME_DUPLICATES_ACROSS)
                 .validator(path_exists_and_is_file)
-                .help("path to the BIG to read"))
+                .help(::i18n::text("list-source-help")))
         .arg(Arg::with_name(ARG_NAME_VERBOSE)
                 .long(ARG_NAME_VERBOSE)
-                .help("if supplied output more information (typically only useful for developing easage itself)"))
+                .help(::i18n::text("list-verbose-help")))
+        .arg(Arg::with_name(ARG_NAME_LENIENT_MAGIC)
+                .long(ARG_NAME_LENIENT_MAGIC)
+                .help(::i18n::text("list-lenient-magic-help")))
+        .arg(Arg::with_name(ARG_NAME_DUPLICATES_ACROSS)
+                .long(ARG_NAME_DUPLICATES_ACROSS)
+                .value_name("directory")
+                .takes_value(true)
+                .help(::i18n::text("list-duplicates-across-help")))
+        .args(&output::args())
 }
 
 pub fn run(args: &ArgMatches) -> CliResult<()> {
+    if let Some(directory) = args.value_of(ARG_NAME_DUPLICATES_ACROSS) {
+        return run_duplicates(directory, &Output::from_args(args));
+    }
+
     let path = args.value_of(ARG_NAME).unwrap();
     let is_verbose = args.is_present(ARG_NAME_VERBOSE);
+    let output = Output::from_args(args);
+
+    let archive = Archive::from_path(path)?
+        .with_lenient_magic(args.is_present(ARG_NAME_LENIENT_MAGIC));
+    let kind = archive.read_kind()?;
+    let table = archive.read_entry_metadata_table()?;
 
-    let mut archive = Archive::from_path(path)?;
+    let mut entry_info = table.iter()
+        .map(|(name, entry)| (name, entry.offset, entry.len, entry.index))
+        .collect::<Vec<_>>();
 
-    let kind = match archive.read_kind() {
-        Ok(kind) => kind,
-        Err(Error::InvalidMagic { magic }) => {
-            eprintln!("Unknown archive type {:?}. Aborting.", magic);
-            return Ok(());
-        },
-        Err(e) => {
-            eprintln!("{}", e);
-            return Ok(());
-        },
-    };
+    entry_info.sort_by(|e1, e2| (*e1.0).cmp(e2.0));
 
-    let table = archive.read_entry_metadata_table()?;
+    if !output.is_text() {
+        let rows = entry_info.iter()
+            .map(|&(name, offset, len, index)| vec![name.as_str().into(), offset.into(), len.into(), (index as u64).into()])
+            .collect::<Vec<_>>();
+
+        output.print_rows(&["name", "offset", "len", "index"], &rows);
+        return Ok(());
+    }
 
     if is_verbose {
-        println!("Archive:");
-        println!("  kind: {:?}", kind);
-        println!("  size: {:?}", archive.read_size()?);
-        println!("  len: {:?}", archive.read_len()?);
+        println!("{}", tr!("list-archive"));
+        println!("  {}", tr!("list-kind", kind = format!("{:?}", kind)));
+        println!("  {}", tr!("list-size", size = archive.read_size()?));
+        println!("  {}", tr!("list-len", len = archive.read_len()?));
 
         if let Some(data) = archive.read_secret_data(&table)? {
             if let Ok(s) = ::std::str::from_utf8(data) {
-                println!("  secret data: {:#?}", s);
+                println!("  {}", tr!("list-secret-data", data = format!("{:#?}", s)));
             }
 
-            println!("  secret data len: {}", data.len());
+            println!("  {}", tr!("list-secret-data-len", len = data.len()));
         }
 
-        println!("  data start: 0x{:x}", archive.read_data_start()?);
+        println!("  {}", tr!("list-data-start", offset = format!("0x{:x}", archive.read_data_start()?)));
     }
 
-    let mut entry_info = table.iter()
-        .map(|(name, entry)| (name, entry.offset, entry.len))
-        .collect::<Vec<_>>();
-
-    entry_info.sort_by(|e1, e2| (*e1.0).cmp(e2.0));
-
     if is_verbose {
-        println!("Entries:");
+        println!("{}", tr!("list-entries"));
     }
 
     for entry in entry_info {
         if is_verbose {
             println!("  {}", entry.0);
-            println!("    offset: 0x{:x}", entry.1);
-            println!("    len: {}", entry.2);
+            println!("    {}", tr!("list-offset", offset = format!("0x{:x}", entry.1)));
+            println!("    {}", tr!("list-len", len = entry.2));
+            println!("    {}", tr!("list-index", index = entry.3));
         } else {
             println!("{}", entry.0);
         }
@@ -95,3 +99,44 @@ pub fn run(args: &ArgMatches) -> CliResult<()> {
 
     Ok(())
 }
+
+/// List every entry provided by more than one archive in `directory` in load
+/// order, marking the copy the game uses.
+fn run_duplicates(directory: &str, output: &Output) -> CliResult<()> {
+    let set = ArchiveSet::from_directory(directory)?;
+    let conflicts = set.conflicts();
+
+    if !output.is_text() {
+        let mut rows = vec![];
+        for conflict in &conflicts {
+            for (idx, provider) in conflict.providers.iter().enumerate() {
+                rows.push(vec![
+                    conflict.name.into(),
+                    provider.path.display().to_string().into(),
+                    provider.entry.len.into(),
+                    (idx == 0).into(),
+                ]);
+            }
+        }
+
+        output.print_rows(&["name", "archive", "len", "wins"], &rows);
+        return Ok(());
+    }
+
+    if conflicts.is_empty() {
+        println!("{}", tr!("list-no-duplicates", count = set.members().len(), directory = directory));
+        return Ok(());
+    }
+
+    for conflict in &conflicts {
+        println!("{}", conflict.name);
+
+        for (idx, provider) in conflict.providers.iter().enumerate() {
+            let id = if idx == 0 { "list-duplicate-wins" } else { "list-duplicate-shadowed" };
+            println!("  {}", tr!(id, path = provider.path.display(), len = provider.entry.len));
+        }
+    }
+
+    println!("{}", tr!("list-duplicates", count = conflicts.len()));
+    Ok(())
+}
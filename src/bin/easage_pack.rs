@@ -1,10 +1,16 @@
 use clap::{Arg, ArgMatches, App, SubCommand};
 
-use ::std::fs::OpenOptions;
+use ::std::fs::{self, OpenOptions};
 use ::std::io::Write;
+use ::std::path::{Path, PathBuf, MAIN_SEPARATOR};
+use ::std::sync::Mutex;
+use ::std::sync::atomic::{AtomicUsize, Ordering};
+use ::std::thread;
 
-use ::lib::{Kind, packer};
-use ::{CliResult, CliError};
+use ::lib::{Archive, packer};
+use ::digest;
+use ::io_args;
+use ::{CliResult, CliError, parse_kind, warn_if_custom_kind};
 
 pub const COMMAND_NAME: &'static str = "pack";
 const ARG_NAME_SOURCE: &'static str = "source";
@@ -12,40 +18,52 @@ const ARG_NAME_OUTPUT: &'static str = "output";
 const ARG_NAME_KIND: &'static str = "kind";
 const ARG_NAME_STRIP_PREFIX: &'static str = "strip-prefix";
 const ARG_NAME_ORDER: &'static str = "order";
+const ARG_NAME_STORE_METADATA: &'static str = "store-metadata";
+const ARG_NAME_CHECKSUMS: &'static str = "checksums";
+const ARG_NAME_BATCH: &'static str = "batch";
+const ARG_NAME_JOBS: &'static str = "jobs";
+const ARG_NAME_DRY_RUN: &'static str = "dry-run";
+const ARG_NAME_SEPARATOR: &'static str = "separator";
+const ARG_NAME_DIRECTORY_MARKERS: &'static str = "directory-markers";
+const ARG_NAME_INHERIT_ORDER: &'static str = "inherit-order";
+const ARG_NAME_INHERIT_SECRET_DATA: &'static str = "inherit-secret-data";
 
 const ARG_VALUE_KIND_BIGF: &'static str = "BIGF";
-const ARG_VALUE_KIND_BIG4: &'static str = "BIG4";
+const ARG_VALUE_KIND_AUTO: &'static str = "auto";
 const ARG_VALUE_ORDER_SMALLEST_TO_LARGEST: &'static str = "smallest-to-largest";
 const ARG_VALUE_ORDER_PATH: &'static str = "path";
+const ARG_VALUE_SEPARATOR_BACKSLASH: &'static str = "backslash";
+const ARG_VALUE_SEPARATOR_SLASH: &'static str = "slash";
 
 pub fn get_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name(COMMAND_NAME)
-        .about("Recursively package a directory structure into a BIG archive")
+        .about(::i18n::text("pack-about"))
         .author("Taryn Hill <taryn@phrohdoh.com>")
         .arg(Arg::with_name(ARG_NAME_SOURCE)
                 .long(ARG_NAME_SOURCE)
                 .value_name(ARG_NAME_SOURCE)
                 .takes_value(true)
                 .required(true)
-                .help("path to the directory to pack into a BIG archive"))
+                .help(::i18n::text("pack-source-help")))
         .arg(Arg::with_name(ARG_NAME_OUTPUT)
                 .long(ARG_NAME_OUTPUT)
                 .value_name(ARG_NAME_OUTPUT)
                 .takes_value(true)
                 .required(true)
-                .help("path to the output BIG archive"))
+                .help(::i18n::text("pack-output-help")))
         .arg(Arg::with_name(ARG_NAME_KIND)
                 .long(ARG_NAME_KIND)
                 .value_name(ARG_NAME_KIND)
                 .takes_value(true)
                 .default_value(ARG_VALUE_KIND_BIGF)
-                .possible_values(&[ARG_VALUE_KIND_BIGF, ARG_VALUE_KIND_BIG4])
-                .help("use BIG4 for the Battle for Middle-Earth series or BIGF for Generals / Zero-Hour"))
+                .validator(validate_kind)
+                .help(::i18n::text("pack-kind-help")))
         .arg(Arg::with_name(ARG_NAME_STRIP_PREFIX)
                 .long(ARG_NAME_STRIP_PREFIX)
                 .value_name(ARG_NAME_STRIP_PREFIX)
                 .takes_value(true)
-                .help("a prefix to strip from entry names"))
+                .conflicts_with(ARG_NAME_BATCH)
+                .help(::i18n::text("pack-strip-prefix-help")))
         .arg(Arg::with_name(ARG_NAME_ORDER)
                 .long(ARG_NAME_ORDER)
                 .value_name(ARG_NAME_ORDER)
@@ -53,60 +71,259 @@ pub fn get_command<'a, 'b>() -> App<'a, 'b> {
                 .default_value(ARG_VALUE_ORDER_PATH)
                 .validator(validate_order)
                 .possible_values(&[ARG_VALUE_ORDER_SMALLEST_TO_LARGEST, ARG_VALUE_ORDER_PATH])
-                .help("criteria used to determine entry order in the archive"))
+                .help(::i18n::text("pack-order-help")))
+        .arg(Arg::with_name(ARG_NAME_STORE_METADATA)
+                .long(ARG_NAME_STORE_METADATA)
+                .help(::i18n::text("pack-store-metadata-help")))
+        .arg(Arg::with_name(ARG_NAME_CHECKSUMS)
+                .long(ARG_NAME_CHECKSUMS)
+                .help(::i18n::text("pack-checksums-help")))
+        .arg(Arg::with_name(ARG_NAME_DIRECTORY_MARKERS)
+                .long(ARG_NAME_DIRECTORY_MARKERS)
+                .help(::i18n::text("pack-directory-markers-help")))
+        .arg(Arg::with_name(ARG_NAME_BATCH)
+                .long(ARG_NAME_BATCH)
+                .help(::i18n::text("pack-batch-help")))
+        .arg(Arg::with_name(ARG_NAME_JOBS)
+                .long(ARG_NAME_JOBS)
+                .value_name("count")
+                .takes_value(true)
+                .requires(ARG_NAME_BATCH)
+                .validator(validate_jobs)
+                .help(::i18n::text("pack-jobs-help")))
+        .arg(Arg::with_name(ARG_NAME_SEPARATOR)
+                .long(ARG_NAME_SEPARATOR)
+                .value_name(ARG_NAME_SEPARATOR)
+                .takes_value(true)
+                .default_value(ARG_VALUE_SEPARATOR_BACKSLASH)
+                .possible_values(&[ARG_VALUE_SEPARATOR_BACKSLASH, ARG_VALUE_SEPARATOR_SLASH])
+                .help(::i18n::text("pack-separator-help")))
+        .arg(Arg::with_name(ARG_NAME_INHERIT_ORDER)
+                .long(ARG_NAME_INHERIT_ORDER)
+                .help(::i18n::text("pack-inherit-order-help")))
+        .arg(Arg::with_name(ARG_NAME_INHERIT_SECRET_DATA)
+                .long(ARG_NAME_INHERIT_SECRET_DATA)
+                .help(::i18n::text("pack-inherit-secret-data-help")))
+        .arg(Arg::with_name(ARG_NAME_DRY_RUN)
+                .long(ARG_NAME_DRY_RUN)
+                .help(::i18n::text("pack-dry-run-help")))
+        .arg(digest::arg()
+                .conflicts_with(ARG_NAME_DRY_RUN))
+        .arg(io_args::buffer_size_arg())
+        .arg(io_args::sequential_hint_arg())
+        .arg(io_args::preallocate_arg())
 }
 
 pub fn run(args: &ArgMatches) -> CliResult<()> {
     let source = args.value_of(ARG_NAME_SOURCE).unwrap();
     let output = args.value_of(ARG_NAME_OUTPUT).unwrap();
 
+    let kind = args.value_of(ARG_NAME_KIND).unwrap();
+    if kind != ARG_VALUE_KIND_AUTO {
+        warn_if_custom_kind(parse_kind(kind).unwrap());
+    }
+
+    if args.is_present(ARG_NAME_BATCH) {
+        let jobs = args.value_of(ARG_NAME_JOBS).unwrap_or("1").parse().unwrap();
+        return run_batch(args, Path::new(source), Path::new(output), jobs);
+    }
+
+    let strip_prefix = args.value_of(ARG_NAME_STRIP_PREFIX)
+        .map(|s| s.to_string());
+
+    pack_to_file(args, source, strip_prefix, Path::new(output))
+}
+
+fn settings_from_args(args: &ArgMatches, strip_prefix: Option<String>) -> packer::Settings {
     let entry_order_criteria = args.value_of(ARG_NAME_ORDER)
         .map(arg_order_to_enum)
         .unwrap();
 
-    let strip_prefix = args.value_of(ARG_NAME_STRIP_PREFIX)
-        .map(|s| s.to_string());
+    // With 'auto' this is replaced by `inherit_from_output`.
+    let kind = match args.value_of(ARG_NAME_KIND).unwrap() {
+        ARG_VALUE_KIND_AUTO => packer::Settings::default().kind,
+        kind => parse_kind(kind).unwrap(),
+    };
 
-    let kind = args.value_of(ARG_NAME_KIND).unwrap();
-    let kind = Kind::try_from_bytes(kind.as_bytes()).unwrap();
+    let store_metadata = args.is_present(ARG_NAME_STORE_METADATA);
+    let store_checksums = args.is_present(ARG_NAME_CHECKSUMS);
+
+    let separator = match args.value_of(ARG_NAME_SEPARATOR).unwrap() {
+        ARG_VALUE_SEPARATOR_SLASH => packer::Separator::Slash,
+        _ => packer::Separator::Backslash,
+    };
 
-    let settings = packer::Settings {
+    packer::Settings {
         entry_order_criteria,
         strip_prefix,
         kind,
-    };
+        store_metadata,
+        store_checksums,
+        separator,
+        directory_markers: args.is_present(ARG_NAME_DIRECTORY_MARKERS),
+        io: io_args::from_args(args),
+        ..packer::Settings::default()
+    }
+}
+
+fn pack_to_file<P: AsRef<Path>>(args: &ArgMatches, source: P, strip_prefix: Option<String>, output: &Path) -> CliResult<()> {
+    let mut settings = settings_from_args(args, strip_prefix);
+    inherit_from_output(args, &mut settings, output)?;
+
+    if args.is_present(ARG_NAME_DRY_RUN) {
+        let plan = packer::plan_directory(source, &settings)
+            .map_err(|e_lib| CliError::PackArchive { inner: e_lib })?;
 
-    let archive = packer::pack_directory(&source, settings)
+        print_plan(&plan, output);
+        return Ok(());
+    }
+
+    let io = settings.io;
+    let archive = packer::pack_directory(source, settings)
         .map_err(|e_lib| CliError::PackArchive { inner: e_lib })?;
 
-    let mut file = OpenOptions::new()
+    let file = OpenOptions::new()
         .read(true)
         .write(true)
         .create(true)
         .truncate(true)
         .open(output)
-        .map_err(|e| CliError::IO {
-            inner: e,
-            path: output.to_string(),
-        })?;
+        .map_err(|e| CliError::io(e, "open", output))?;
 
     let data = archive.as_slice();
-    file.write_all(data)?;
+    io.preallocate(&file, data.len() as u64)
+        .map_err(|e| CliError::io(e, "allocate", output))?;
+
+    let mut writer = io.buffered(file);
+    writer.write_all(data)
+        .and_then(|_| writer.flush())
+        .map_err(|e| CliError::io(e, "write", output))?;
+
+    if digest::is_requested(args) {
+        digest::print(&digest::sha256(data), output);
+    }
+
+    Ok(())
+}
+
+/// Apply `--kind auto`, `--inherit-order`, and `--inherit-secret-data` by
+/// reading the archive that is about to be replaced.
+fn inherit_from_output(args: &ArgMatches, settings: &mut packer::Settings, output: &Path) -> CliResult<()> {
+    let inherit_kind = args.value_of(ARG_NAME_KIND) == Some(ARG_VALUE_KIND_AUTO);
+    let inherit_order = args.is_present(ARG_NAME_INHERIT_ORDER);
+    let inherit_secret_data = args.is_present(ARG_NAME_INHERIT_SECRET_DATA);
+
+    if !inherit_kind && !inherit_order && !inherit_secret_data {
+        return Ok(());
+    }
+
+    if !output.is_file() {
+        return Err(CliError::NotFound {
+            message: tr!("pack-no-inherit-source", path = output.display()),
+        });
+    }
+
+    // The archive is dropped before `output` is truncated and rewritten.
+    let archive = Archive::from_path(output)?
+        .with_lenient_magic(true);
+    let table = archive.read_entry_metadata_table()?;
+
+    if inherit_kind {
+        settings.kind = archive.read_kind()?;
+    }
+
+    if inherit_order {
+        settings.preferred_order = table.values()
+            .map(|entry| entry.name.clone())
+            .collect();
+    }
+
+    if inherit_secret_data {
+        settings.secret_data = archive.read_secret_data(&table)?
+            .map(|data| data.to_vec())
+            .unwrap_or_default();
+    }
+
     Ok(())
 }
 
+/// Print `plan` at once so the plans of archives packed in parallel do not interleave.
+fn print_plan(plan: &packer::PackPlan, output: &Path) {
+    let replacing = if output.exists() { ::i18n::text("replacing-existing-file") } else { "" };
+
+    let mut text = tr!("plan-header",
+        path = output.display(),
+        count = plan.entries.len(),
+        size = plan.size.total(),
+        replacing = replacing);
+    text.push('\n');
+
+    for entry in &plan.entries {
+        text.push_str(&format!("  {}\n", tr!("plan-entry", name = entry.name, size = entry.size)));
+    }
+
+    print!("{}", text);
+}
+
+/// Pack every immediate subdirectory of `source` into `output/<name>.big`
+/// using `jobs` threads.
+///
+/// Every subdirectory is attempted even if some fail.
+fn run_batch(args: &ArgMatches, source: &Path, output: &Path, jobs: usize) -> CliResult<()> {
+    let mut directories = fs::read_dir(source)
+        .map_err(|e| CliError::io(e, "read", source))?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| path.is_dir())
+        .collect::<Vec<PathBuf>>();
+
+    directories.sort();
+
+    if !args.is_present(ARG_NAME_DRY_RUN) {
+        fs::create_dir_all(output)
+            .map_err(|e| CliError::io(e, "create", output))?;
+    }
+
+    let next = AtomicUsize::new(0);
+    let failures = Mutex::new(vec![]);
+
+    thread::scope(|scope| {
+        for _ in 0..jobs {
+            scope.spawn(|| {
+                while let Some(directory) = directories.get(next.fetch_add(1, Ordering::SeqCst)) {
+                    let name = directory.file_name().unwrap().to_string_lossy();
+                    let archive_path = output.join(format!("{}.big", name));
+                    let strip_prefix = format!("{}{}", directory.display(), MAIN_SEPARATOR);
+
+                    match pack_to_file(args, directory, Some(strip_prefix), &archive_path) {
+                        Ok(()) if args.is_present(ARG_NAME_DRY_RUN) => {},
+                        Ok(()) => println!("{}", tr!("pack-packed", directory = directory.display(), path = archive_path.display())),
+                        Err(e) => {
+                            eprintln!("{}", tr!("error-in", path = directory.display(), message = e));
+                            failures.lock().unwrap().push(directory.clone());
+                        },
+                    }
+                }
+            });
+        }
+    });
+
+    let failures = failures.into_inner().unwrap();
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(CliError::Custom {
+            message: tr!("pack-batch-failed", failed = failures.len(), total = directories.len()),
+        })
+    }
+}
+
 fn arg_order_to_enum(input: &str) -> packer::EntryOrderCriteria {
     match input {
         ARG_VALUE_ORDER_SMALLEST_TO_LARGEST => packer::EntryOrderCriteria::SmallestToLargest,
         ARG_VALUE_ORDER_PATH => packer::EntryOrderCriteria::Path,
         _  => {
-            eprintln!(r#"
-Unexpected error!
-Please file a bug at https://github.com/Phrohdoh/easage/issues/new and provide the following text:
-
-Invalid input to 'arg_order_to_enum': {:?}
-Did you validate input via 'validate_order'?
-"#, input);
+            eprintln!("\n{}\n", tr!("pack-unexpected-order", input = format!("{:?}", input)));
 
             ::std::process::exit(1);
         },
@@ -117,9 +334,24 @@ fn validate_order(v: String) -> Result<(), String> {
     if v == ARG_VALUE_ORDER_SMALLEST_TO_LARGEST || v == ARG_VALUE_ORDER_PATH {
         Ok(())
     } else {
-        Err(format!("{} must be one of '{}' or '{}'",
-            ARG_NAME_ORDER,
-            ARG_VALUE_ORDER_SMALLEST_TO_LARGEST,
-            ARG_VALUE_ORDER_PATH))
+        Err(tr!("invalid-one-of",
+            arg = ARG_NAME_ORDER,
+            first = ARG_VALUE_ORDER_SMALLEST_TO_LARGEST,
+            second = ARG_VALUE_ORDER_PATH))
     }
-}
\ No newline at end of file
+}
+
+fn validate_kind(v: String) -> Result<(), String> {
+    if v == ARG_VALUE_KIND_AUTO {
+        Ok(())
+    } else {
+        parse_kind(&v).map(|_| ())
+    }
+}
+
+fn validate_jobs(v: String) -> Result<(), String> {
+    match v.parse::<usize>() {
+        Ok(jobs) if jobs > 0 => Ok(()),
+        _ => Err(tr!("invalid-positive", arg = ARG_NAME_JOBS)),
+    }
+}
@@ -0,0 +1,210 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+
+use ::std::ffi::OsString;
+use ::std::fs::{self, File};
+use ::std::io::Write;
+use ::std::path::{Path, PathBuf};
+use ::std::process;
+
+use ::lib::{Archive, packer};
+use ::digest::{self, DigestWriter};
+use ::io_args;
+use ::{CliResult, CliError, parse_kind, parse_number, path_exists_and_is_file, warn_if_custom_kind};
+
+pub const COMMAND_NAME: &'static str = "repack";
+const ARG_NAME_SOURCE: &'static str = "source";
+const ARG_NAME_OUTPUT: &'static str = "output";
+const ARG_NAME_KIND: &'static str = "kind";
+const ARG_NAME_ORDER: &'static str = "order";
+const ARG_NAME_SEPARATOR: &'static str = "separator";
+const ARG_NAME_DEDUPLICATE: &'static str = "deduplicate";
+const ARG_NAME_ALIGN: &'static str = "align";
+const ARG_NAME_STRIP_SECRET_DATA: &'static str = "strip-secret-data";
+const ARG_NAME_DRY_RUN: &'static str = "dry-run";
+
+const ARG_VALUE_ORDER_SMALLEST_TO_LARGEST: &'static str = "smallest-to-largest";
+const ARG_VALUE_ORDER_PATH: &'static str = "path";
+const ARG_VALUE_SEPARATOR_BACKSLASH: &'static str = "backslash";
+const ARG_VALUE_SEPARATOR_SLASH: &'static str = "slash";
+
+pub fn get_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about(::i18n::text("repack-about"))
+        .author("Taryn Hill <taryn@phrohdoh.com>")
+        .arg(Arg::with_name(ARG_NAME_SOURCE)
+                .long(ARG_NAME_SOURCE)
+                .value_name(ARG_NAME_SOURCE)
+                .takes_value(true)
+                .required(true)
+                .validator(path_exists_and_is_file)
+                .help(::i18n::text("repack-source-help")))
+        .arg(Arg::with_name(ARG_NAME_OUTPUT)
+                .long(ARG_NAME_OUTPUT)
+                .value_name(ARG_NAME_OUTPUT)
+                .takes_value(true)
+                .required(true)
+                .help(::i18n::text("repack-output-help")))
+        .arg(Arg::with_name(ARG_NAME_KIND)
+                .long(ARG_NAME_KIND)
+                .value_name(ARG_NAME_KIND)
+                .takes_value(true)
+                .validator(|v| parse_kind(&v).map(|_| ()))
+                .help(::i18n::text("repack-kind-help")))
+        .arg(Arg::with_name(ARG_NAME_ORDER)
+                .long(ARG_NAME_ORDER)
+                .value_name(ARG_NAME_ORDER)
+                .takes_value(true)
+                .possible_values(&[ARG_VALUE_ORDER_SMALLEST_TO_LARGEST, ARG_VALUE_ORDER_PATH])
+                .help(::i18n::text("repack-order-help")))
+        .arg(Arg::with_name(ARG_NAME_SEPARATOR)
+                .long(ARG_NAME_SEPARATOR)
+                .value_name(ARG_NAME_SEPARATOR)
+                .takes_value(true)
+                .possible_values(&[ARG_VALUE_SEPARATOR_BACKSLASH, ARG_VALUE_SEPARATOR_SLASH])
+                .help(::i18n::text("repack-separator-help")))
+        .arg(Arg::with_name(ARG_NAME_DEDUPLICATE)
+                .long(ARG_NAME_DEDUPLICATE)
+                .help(::i18n::text("repack-deduplicate-help")))
+        .arg(Arg::with_name(ARG_NAME_ALIGN)
+                .long(ARG_NAME_ALIGN)
+                .value_name("bytes")
+                .takes_value(true)
+                .validator(validate_align)
+                .help(::i18n::text("repack-align-help")))
+        .arg(Arg::with_name(ARG_NAME_STRIP_SECRET_DATA)
+                .long(ARG_NAME_STRIP_SECRET_DATA)
+                .help(::i18n::text("repack-strip-secret-data-help")))
+        .arg(Arg::with_name(ARG_NAME_DRY_RUN)
+                .long(ARG_NAME_DRY_RUN)
+                .help(::i18n::text("repack-dry-run-help")))
+        .arg(digest::arg()
+                .conflicts_with(ARG_NAME_DRY_RUN))
+        .arg(io_args::buffer_size_arg())
+        .arg(io_args::sequential_hint_arg())
+}
+
+pub fn run(args: &ArgMatches) -> CliResult<()> {
+    let source = args.value_of(ARG_NAME_SOURCE).unwrap();
+    let output = Path::new(args.value_of(ARG_NAME_OUTPUT).unwrap());
+
+    let settings = settings_from_args(args);
+    if let Some(kind) = settings.kind {
+        warn_if_custom_kind(kind);
+    }
+
+    // Archives with custom magic are repacked as-is unless --kind is given.
+    let archive = Archive::from_path(source)?
+        .with_lenient_magic(true);
+
+    if args.is_present(ARG_NAME_DRY_RUN) {
+        let repacked = packer::repack(&archive, &settings)?;
+        let table = repacked.read_entry_metadata_table()?;
+
+        let replacing = if output.exists() { ::i18n::text("replacing-existing-file") } else { "" };
+        println!("{}", tr!("plan-header",
+            path = output.display(),
+            count = table.len(),
+            size = repacked.as_slice().len(),
+            replacing = replacing));
+
+        for entry in &table {
+            println!("  {}", tr!("plan-entry", name = entry.name, size = entry.len));
+        }
+
+        return Ok(());
+    }
+
+    // The output may be the source, which is still mapped, so write next to
+    // it and only replace it once everything has been written.
+    let temp_path = temp_path(output);
+
+    let result = write_to(&archive, &settings, &temp_path);
+    drop(archive);
+
+    let sha256 = result?;
+
+    if let Err(e) = fs::rename(&temp_path, output) {
+        let _ = fs::remove_file(&temp_path);
+        return Err(CliError::io(e, "replace", output));
+    }
+
+    if digest::is_requested(args) {
+        digest::print(&sha256, output);
+    }
+
+    Ok(())
+}
+
+/// Where to write an archive that will replace `output` once complete.
+pub fn temp_path(output: &Path) -> PathBuf {
+    let mut temp_name = output.file_name().map(OsString::from).unwrap_or_default();
+    temp_name.push(format!(".{}.repack", process::id()));
+    output.with_file_name(temp_name)
+}
+
+/// Write `archive` to the new file `path`, returning the SHA-256 of what was written.
+///
+/// An existing file at `path` is never touched. If writing fails the new file
+/// is removed.
+pub fn write_to(archive: &Archive, settings: &packer::RepackSettings, path: &Path) -> CliResult<Vec<u8>> {
+    let file = File::options().write(true).create_new(true).open(path)
+        .map_err(|e| CliError::io(e, "create", path))?;
+
+    let result = write_file(archive, settings, file, path);
+    if result.is_err() {
+        let _ = fs::remove_file(path);
+    }
+
+    result
+}
+
+fn write_file(archive: &Archive, settings: &packer::RepackSettings, file: File, path: &Path) -> CliResult<Vec<u8>> {
+    let mut writer = DigestWriter::new(settings.io.buffered(file));
+    packer::repack_to(archive, settings, &mut writer)
+        .map_err(|e_lib| match e_lib {
+            ::lib::Error::IO { inner, .. } => CliError::io(inner, "write", path),
+            inner => CliError::Lib { inner },
+        })?;
+
+    let (mut writer, sha256) = writer.finish();
+    writer.flush()
+        .map_err(|e| CliError::io(e, "write", path))?;
+
+    Ok(sha256)
+}
+
+fn settings_from_args(args: &ArgMatches) -> packer::RepackSettings {
+    let entry_order_criteria = args.value_of(ARG_NAME_ORDER).map(|order| match order {
+        ARG_VALUE_ORDER_SMALLEST_TO_LARGEST => packer::EntryOrderCriteria::SmallestToLargest,
+        _ => packer::EntryOrderCriteria::Path,
+    });
+
+    let kind = args.value_of(ARG_NAME_KIND)
+        .map(|kind| parse_kind(kind).unwrap());
+
+    let separator = args.value_of(ARG_NAME_SEPARATOR).map(|separator| match separator {
+        ARG_VALUE_SEPARATOR_SLASH => packer::Separator::Slash,
+        _ => packer::Separator::Backslash,
+    });
+
+    let alignment = args.value_of(ARG_NAME_ALIGN)
+        .map(|v| parse_number(v).unwrap())
+        .unwrap_or(1);
+
+    packer::RepackSettings {
+        entry_order_criteria,
+        kind,
+        separator,
+        deduplicate: args.is_present(ARG_NAME_DEDUPLICATE),
+        alignment,
+        strip_secret_data: args.is_present(ARG_NAME_STRIP_SECRET_DATA),
+        io: io_args::from_args(args),
+    }
+}
+
+fn validate_align(v: String) -> Result<(), String> {
+    match parse_number(&v)? {
+        0 => Err(tr!("invalid-at-least-one", arg = ARG_NAME_ALIGN)),
+        _ => Ok(()),
+    }
+}
@@ -1,94 +1,169 @@
-use ::std::fs::{self, OpenOptions};
-use ::std::io::Write;
-use ::std::path::PathBuf;
 use clap::{Arg, ArgMatches, ArgGroup, App, SubCommand};
 
+use ::std::path::PathBuf;
+
 use ::lib::Archive;
-use ::{CliResult, CliError};
+use ::lib::extract::{self, CaseCollision, ExtractOptions, OnConflict, Resume};
+use ::io_args;
+use ::CliResult;
 
 pub const COMMAND_NAME: &'static str = "unpack";
 const ARG_NAME_SOURCE: &'static str = "source";
 const ARG_NAME_OUTPUT: &'static str = "output";
 const ARG_NAME_NAMES: &'static str = "names";
 const ARG_NAME_ALL: &'static str = "all";
+const ARG_NAME_IGNORE_METADATA: &'static str = "ignore-metadata";
+const ARG_NAME_RESUME: &'static str = "resume";
+const ARG_NAME_CHECKSUM: &'static str = "checksum";
+const ARG_NAME_OVERWRITE: &'static str = "overwrite";
+const ARG_NAME_SKIP_EXISTING: &'static str = "skip-existing";
+const ARG_NAME_BACKUP: &'static str = "backup";
+const ARG_NAME_FLATTEN: &'static str = "flatten";
+const ARG_NAME_PREFIX: &'static str = "prefix";
+const ARG_NAME_CASE_COLLISIONS: &'static str = "case-collisions";
+
+const ARG_VALUE_CASE_COLLISIONS_FAIL: &'static str = "fail";
+const ARG_VALUE_CASE_COLLISIONS_RENAME: &'static str = "rename";
+const ARG_VALUE_CASE_COLLISIONS_ALLOW: &'static str = "allow";
 
 pub fn get_command<'a, 'b>() -> App<'a, 'b> {
     SubCommand::with_name(COMMAND_NAME)
-        .about("Unpack the contents of a BIG archive")
+        .about(::i18n::text("unpack-about"))
         .author("Taryn Hill <taryn@phrohdoh.com>")
         .arg(Arg::with_name(ARG_NAME_SOURCE)
                 .long(ARG_NAME_SOURCE)
                 .value_name(ARG_NAME_SOURCE)
                 .required(true)
-                .help("path to the BIG archive to unpack files from"))
+                .help(::i18n::text("unpack-source-help")))
         .arg(Arg::with_name(ARG_NAME_OUTPUT)
                 .long(ARG_NAME_OUTPUT)
                 .value_name(ARG_NAME_OUTPUT)
                 .takes_value(true)
                 .required(true)
-                .help("path to the directory to write files to"))
+                .help(::i18n::text("unpack-output-help")))
         .arg(Arg::with_name(ARG_NAME_NAMES)
                 .long(ARG_NAME_NAMES)
                 .value_name(ARG_NAME_NAMES)
                 .multiple(true)
-                .help("one or more entry names to extract"))
+                .help(::i18n::text("unpack-names-help")))
         .arg(Arg::with_name(ARG_NAME_ALL)
                 .long(ARG_NAME_ALL)
-                .conflicts_with(ARG_NAME_NAMES)
-                .help("unpack all entries"))
+                .help(::i18n::text("unpack-all-help")))
+        .arg(Arg::with_name(ARG_NAME_IGNORE_METADATA)
+                .long(ARG_NAME_IGNORE_METADATA)
+                .help(::i18n::text("unpack-ignore-metadata-help")))
+        .arg(Arg::with_name(ARG_NAME_RESUME)
+                .long(ARG_NAME_RESUME)
+                .help(::i18n::text("unpack-resume-help")))
+        .arg(Arg::with_name(ARG_NAME_CHECKSUM)
+                .long(ARG_NAME_CHECKSUM)
+                .requires(ARG_NAME_RESUME)
+                .help(::i18n::text("unpack-checksum-help")))
+        .arg(Arg::with_name(ARG_NAME_OVERWRITE)
+                .long(ARG_NAME_OVERWRITE)
+                .help(::i18n::text("unpack-overwrite-help")))
+        .arg(Arg::with_name(ARG_NAME_SKIP_EXISTING)
+                .long(ARG_NAME_SKIP_EXISTING)
+                .help(::i18n::text("unpack-skip-existing-help")))
+        .arg(Arg::with_name(ARG_NAME_BACKUP)
+                .long(ARG_NAME_BACKUP)
+                .help(::i18n::text("unpack-backup-help")))
+        .arg(Arg::with_name(ARG_NAME_FLATTEN)
+                .long(ARG_NAME_FLATTEN)
+                .help(::i18n::text("unpack-flatten-help")))
+        .arg(Arg::with_name(ARG_NAME_PREFIX)
+                .long(ARG_NAME_PREFIX)
+                .value_name("dir")
+                .takes_value(true)
+                .help(::i18n::text("unpack-prefix-help")))
+        .arg(Arg::with_name(ARG_NAME_CASE_COLLISIONS)
+                .long(ARG_NAME_CASE_COLLISIONS)
+                .value_name("policy")
+                .takes_value(true)
+                .possible_values(&[ARG_VALUE_CASE_COLLISIONS_FAIL, ARG_VALUE_CASE_COLLISIONS_RENAME, ARG_VALUE_CASE_COLLISIONS_ALLOW])
+                .help(::i18n::text("unpack-case-collisions-help")))
+        .arg(io_args::sequential_hint_arg())
+        .arg(io_args::preallocate_arg())
         .group(ArgGroup::with_name("to-extract")
                 .args(&[ARG_NAME_NAMES, ARG_NAME_ALL])
                 .required(true))
+        .group(ArgGroup::with_name("on-conflict")
+                .args(&[ARG_NAME_OVERWRITE, ARG_NAME_SKIP_EXISTING, ARG_NAME_BACKUP]))
 }
 
 pub fn run(args: &ArgMatches) -> CliResult<()> {
     let source = args.value_of(ARG_NAME_SOURCE).unwrap();
     let output = args.value_of(ARG_NAME_OUTPUT).unwrap();
-    let output = PathBuf::from(output);
 
-    let mut names: Option<Vec<_>> = None;
-    let should_unpack_all = args.is_present(ARG_NAME_ALL);
+    let resume = if !args.is_present(ARG_NAME_RESUME) {
+        Resume::Disabled
+    } else if args.is_present(ARG_NAME_CHECKSUM) {
+        Resume::MatchingChecksum
+    } else {
+        Resume::MatchingSize
+    };
 
-    if !should_unpack_all {
-        names = Some(args.values_of(ARG_NAME_NAMES).unwrap().collect::<Vec<_>>());
-    }
+    // Without a policy existing files are an error, except that --resume
+    // exists to replace the partial files an interrupted unpack left behind.
+    let on_conflict = if args.is_present(ARG_NAME_OVERWRITE) {
+        OnConflict::Overwrite
+    } else if args.is_present(ARG_NAME_SKIP_EXISTING) {
+        OnConflict::Skip
+    } else if args.is_present(ARG_NAME_BACKUP) {
+        OnConflict::Backup
+    } else if resume != Resume::Disabled {
+        OnConflict::Overwrite
+    } else {
+        OnConflict::Fail
+    };
+
+    let on_case_collision = match args.value_of(ARG_NAME_CASE_COLLISIONS) {
+        Some(ARG_VALUE_CASE_COLLISIONS_FAIL) => CaseCollision::Fail,
+        Some(ARG_VALUE_CASE_COLLISIONS_RENAME) => CaseCollision::Rename,
+        Some(_) => CaseCollision::Allow,
+        None => CaseCollision::platform_default(),
+    };
 
-    let mut archive = Archive::from_path(source)?;
+    let options = ExtractOptions {
+        resume,
+        on_conflict,
+        restore_metadata: !args.is_present(ARG_NAME_IGNORE_METADATA),
+        flatten: args.is_present(ARG_NAME_FLATTEN),
+        prefix: args.value_of(ARG_NAME_PREFIX).map(PathBuf::from),
+        on_case_collision,
+        io: io_args::from_args(args),
+        ..ExtractOptions::default()
+    };
+
+    let archive = Archive::from_path(source)?;
     let table = archive.read_entry_metadata_table()?;
 
-    for entry_name in table.keys() {
-        if !should_unpack_all {
-            if let Some(names) = names.as_ref() {
-                if names.contains(&entry_name.as_str()) {
-                    continue;
-                }
-            }
-        }
-
-        if let Ok(Some(data)) = archive.get_bytes_via_table(&table, entry_name) {
-            let output_file = {
-                let mut o = output.clone();
-                o.push(entry_name.replace("\\", "/"));
-                o
-            };
-
-            let output_dir = output_file.parent()
-                .ok_or(CliError::Custom {
-                    message: format!("Parent directory for output file {} could not be found.", output_file.display())
-                })?;
-
-            fs::create_dir_all(&output_dir)?;
-
-            let mut f = OpenOptions::new()
-                .create(true)
-                .read(true)
-                .write(true)
-                .truncate(true)
-                .open(&output_file)?;
-
-            let _len = f.write(data)?;
-        }
+    let report = if args.is_present(ARG_NAME_ALL) {
+        extract::extract_all(&archive, &table, output, &options)?
+    } else {
+        let names = args.values_of(ARG_NAME_NAMES).unwrap().collect::<Vec<_>>();
+        extract::extract(&archive, &table, &names, output, &options)?
+    };
+
+    if !report.skipped.is_empty() {
+        println!("{}", tr!("unpack-skipped-resume", count = report.skipped.len()));
+    }
+
+    if !report.skipped_existing.is_empty() {
+        println!("{}", tr!("unpack-skipped-existing", count = report.skipped_existing.len()));
+    }
+
+    for backup in &report.backups {
+        println!("{}", tr!("unpack-backed-up", path = backup.display()));
+    }
+
+    if !report.directories.is_empty() {
+        println!("{}", tr!("unpack-directories", count = report.directories.len()));
+    }
+
+    for (name, path) in &report.renamed {
+        println!("{}", tr!("unpack-renamed", name = name, path = path.display()));
     }
 
     Ok(())
-}
\ No newline at end of file
+}
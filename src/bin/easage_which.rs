@@ -0,0 +1,93 @@
+use clap::{Arg, ArgMatches, App, SubCommand};
+
+use ::lib::archive_set::{self, ArchiveSet};
+use ::glob;
+use ::output::{self, Output};
+use ::{CliResult, CliError};
+
+pub const COMMAND_NAME: &'static str = "which";
+const ARG_NAME_DIRECTORY: &'static str = "directory";
+const ARG_NAME_ENTRY: &'static str = "entry";
+const ARG_NAME_RECURSIVE: &'static str = "recursive";
+
+pub fn get_command<'a, 'b>() -> App<'a, 'b> {
+    SubCommand::with_name(COMMAND_NAME)
+        .about(::i18n::text("which-about"))
+        .author("Taryn Hill <taryn@phrohdoh.com>")
+        .arg(Arg::with_name(ARG_NAME_DIRECTORY)
+                .index(1)
+                .takes_value(true)
+                .required(true)
+                .help(::i18n::text("which-directory-help")))
+        .arg(Arg::with_name(ARG_NAME_ENTRY)
+                .index(2)
+                .takes_value(true)
+                .required(true)
+                .help(::i18n::text("which-entry-help")))
+        .arg(Arg::with_name(ARG_NAME_RECURSIVE)
+                .long(ARG_NAME_RECURSIVE)
+                .help(::i18n::text("which-recursive-help")))
+        .args(&output::args())
+}
+
+pub fn run(args: &ArgMatches) -> CliResult<()> {
+    let directory = args.value_of(ARG_NAME_DIRECTORY).unwrap();
+    let entry = args.value_of(ARG_NAME_ENTRY).unwrap();
+    let is_recursive = args.is_present(ARG_NAME_RECURSIVE);
+    let output = Output::from_args(args);
+
+    let paths = archive_set::find_archives(directory, is_recursive)?;
+    let set = ArchiveSet::from_paths(&paths)?;
+
+    let pattern = archive_set::normalize_name(entry);
+    let mut names = if glob::is_glob(&pattern) {
+        set.names()
+            .filter(|name| glob::matches(&pattern, name))
+            .collect::<Vec<_>>()
+    } else {
+        set.names()
+            .filter(|name| *name == pattern)
+            .collect::<Vec<_>>()
+    };
+
+    if names.is_empty() {
+        return Err(CliError::NotFound {
+            message: tr!("which-not-found", directory = directory, entry = entry),
+        });
+    }
+
+    names.sort();
+
+    if !output.is_text() {
+        let mut rows = vec![];
+        for name in names {
+            for (idx, provider) in set.providers(name).iter().enumerate() {
+                rows.push(vec![
+                    name.into(),
+                    provider.path.display().to_string().into(),
+                    provider.entry.offset.into(),
+                    provider.entry.len.into(),
+                    (idx == 0).into(),
+                ]);
+            }
+        }
+
+        output.print_rows(&["name", "archive", "offset", "len", "wins"], &rows);
+        return Ok(());
+    }
+
+    for name in names {
+        println!("{}", name);
+
+        for (idx, provider) in set.providers(name).iter().enumerate() {
+            let marker = if idx == 0 { "*" } else { " " };
+            println!("  {}", tr!("which-provider",
+                marker = marker,
+                path = provider.path.display(),
+                offset = format!("0x{:x}", provider.entry.offset),
+                len = provider.entry.len));
+        }
+    }
+
+    Ok(())
+}
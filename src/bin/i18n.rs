@@ -0,0 +1,363 @@
+//! The message catalogs that user-facing text is looked up in.
+//!
+//! Catalogs live in `locales/<language>.ftl` and use a subset of the Fluent
+//! syntax: `id = text` messages, `# comments`, indented continuation lines,
+//! and `{ $name }` placeables. English is the fallback for every message a
+//! catalog does not translate.
+//!
+//! Everything easage prints itself is looked up here: the descriptions of the
+//! commands and their options, progress, plans, reports, and errors, including
+//! those of the library. Only the usage and argument errors clap generates on
+//! its own (such as "error: The argument '--source <source>' requires a
+//! value") are not.
+//!
+//! The locale is chosen once at startup from `--lang`, then `EASAGE_LANG`,
+//! `LC_ALL`, `LC_MESSAGES`, and `LANG`.
+
+use std::collections::HashMap;
+use std::env;
+use std::fmt::Display;
+use std::sync::OnceLock;
+
+pub const ARG_NAME_LANG: &'static str = "lang";
+
+const FALLBACK: &'static str = "en";
+
+/// Every catalog compiled into easage, by language tag.
+const CATALOGS: &[(&str, &str)] = &[
+    ("en", include_str!("locales/en.ftl")),
+];
+
+const LOCALE_ENV_VARS: &[&str] = &["EASAGE_LANG", "LC_ALL", "LC_MESSAGES", "LANG"];
+
+static CATALOG: OnceLock<Catalog> = OnceLock::new();
+
+/// A value substituted into a message.
+pub type Value<'a> = dyn Display + 'a;
+
+/// Look up the message `$id`, substituting `{ $name }` placeables with the
+/// given `name = value` pairs.
+macro_rules! tr {
+    ($id:expr) => {
+        ::i18n::format($id, &[])
+    };
+    ($id:expr, $($name:ident = $value:expr),+ $(,)*) => {
+        ::i18n::format($id, &[$((stringify!($name), &$value as &::i18n::Value)),+])
+    };
+}
+
+struct Catalog {
+    messages: HashMap<String, String>,
+}
+
+impl Catalog {
+    /// The messages of the first of `candidates` easage has a catalog for,
+    /// on top of the English messages.
+    fn for_candidates(candidates: &[String]) -> Self {
+        let translation = candidates.iter().filter_map(|tag| catalog_source(tag)).next();
+        Catalog::new(catalog_source(FALLBACK).unwrap(), translation)
+    }
+
+    /// The messages of `translation` on top of those of `fallback`.
+    fn new(fallback: &str, translation: Option<&str>) -> Self {
+        let mut messages = parse(fallback);
+        if let Some(source) = translation {
+            messages.extend(parse(source));
+        }
+
+        Catalog { messages }
+    }
+
+    /// The message `id`, or `id` itself if no catalog has it.
+    fn text<'a>(&'a self, id: &'a str) -> &'a str {
+        self.messages.get(id).map(String::as_str).unwrap_or(id)
+    }
+
+    /// The message `id` with every `{ $name }` replaced by the matching value
+    /// in `args`. Unknown placeables are left as they are.
+    fn format(&self, id: &str, args: &[(&str, &Value)]) -> String {
+        let message = self.text(id);
+        let mut formatted = String::with_capacity(message.len());
+        let mut rest = message;
+
+        while let Some(open) = rest.find('{') {
+            formatted.push_str(&rest[..open]);
+            rest = &rest[open..];
+
+            let close = match rest.find('}') {
+                Some(close) => close,
+                None => break,
+            };
+
+            let name = rest[1..close].trim().trim_start_matches('$');
+            match args.iter().find(|&&(arg, _)| arg == name) {
+                Some(&(_, value)) => formatted.push_str(&value.to_string()),
+                None => formatted.push_str(&rest[..=close]),
+            }
+
+            rest = &rest[close + 1..];
+        }
+
+        formatted.push_str(rest);
+        formatted
+    }
+}
+
+fn catalog_source(tag: &str) -> Option<&'static str> {
+    CATALOGS.iter()
+        .find(|&&(language, _)| language.eq_ignore_ascii_case(tag))
+        .map(|&(_, source)| source)
+}
+
+fn parse(source: &str) -> HashMap<String, String> {
+    let mut messages: HashMap<String, String> = HashMap::new();
+    let mut current: Option<&str> = None;
+    let mut blank_lines = 0;
+
+    for line in source.lines() {
+        let trimmed = line.trim();
+
+        // Blank lines only belong to a message if it continues after them.
+        if trimmed.is_empty() {
+            blank_lines += 1;
+            continue;
+        }
+
+        if line.starts_with(' ') {
+            if let Some(value) = current.and_then(|id| messages.get_mut(id)) {
+                // A message may start on the line after its id.
+                if !value.is_empty() {
+                    value.extend((0..=blank_lines).map(|_| '\n'));
+                }
+
+                value.push_str(trimmed);
+            }
+
+            blank_lines = 0;
+            continue;
+        }
+
+        current = None;
+        blank_lines = 0;
+
+        let line = trimmed;
+        if line.starts_with('#') {
+            continue;
+        }
+
+        if let Some(eq) = line.find('=') {
+            let id = line[..eq].trim();
+            messages.insert(id.to_string(), line[eq + 1..].trim().to_string());
+            current = Some(id);
+        }
+    }
+
+    messages
+}
+
+fn catalog() -> &'static Catalog {
+    CATALOG.get_or_init(|| Catalog::for_candidates(&candidates(None)))
+}
+
+/// Select the catalog from `lang` or, if it is `None`, the environment.
+///
+/// Only the first call has an effect; messages looked up before it use the
+/// environment.
+pub fn init(lang: Option<&str>) {
+    let _ = CATALOG.set(Catalog::for_candidates(&candidates(lang)));
+}
+
+/// The value of `--lang` in the process arguments.
+///
+/// The catalog has to be selected before the command line is parsed as the
+/// command descriptions clap prints are translated too.
+pub fn requested_locale() -> Option<String> {
+    let flag = format!("--{}", ARG_NAME_LANG);
+    let prefix = format!("{}=", flag);
+    let mut args = env::args().skip(1);
+
+    while let Some(arg) = args.next() {
+        if arg == "--" {
+            break;
+        } else if arg == flag {
+            return args.next();
+        } else if arg.starts_with(&prefix) {
+            return Some(arg[prefix.len()..].to_string());
+        }
+    }
+
+    None
+}
+
+/// Language tags to try, most specific first: `pt_BR.UTF-8` yields `pt-BR`
+/// and `pt`.
+fn candidates(lang: Option<&str>) -> Vec<String> {
+    let requested = lang.map(str::to_string).or_else(|| {
+        LOCALE_ENV_VARS.iter()
+            .filter_map(|name| env::var(name).ok())
+            .find(|value| !value.is_empty())
+    });
+
+    let requested = match requested {
+        Some(ref value) if value != "C" && value != "POSIX" => value.clone(),
+        _ => return vec![],
+    };
+
+    // LANGUAGE-style lists ("de:en") are tried in order.
+    let mut tags = vec![];
+    for locale in requested.split(':') {
+        let tag = locale.split(['.', '@']).next().unwrap().replace('_', "-");
+        if tag.is_empty() {
+            continue;
+        }
+
+        if let Some(dash) = tag.find('-') {
+            tags.push(tag.clone());
+            tags.push(tag[..dash].to_string());
+        } else {
+            tags.push(tag);
+        }
+    }
+
+    tags
+}
+
+/// The message `id` without placeables, for strings clap has to borrow for the
+/// life of the program such as `about`.
+pub fn text(id: &'static str) -> &'static str {
+    catalog().text(id)
+}
+
+/// The message `id` with every `{ $name }` replaced by the matching value in
+/// `args`. Unknown placeables are left as they are.
+pub fn format(id: &str, args: &[(&str, &Value)]) -> String {
+    catalog().format(id, args)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SOURCE: &'static str = "\
+# A comment = not a message
+greeting = Hello, { $name }!
+farewell=Bye
+
+multiline =
+    First line
+
+    Third line
+not-continued = Done
+";
+
+    #[test]
+    fn parse_messages() {
+        let messages = parse(SOURCE);
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages["greeting"], "Hello, { $name }!");
+        assert_eq!(messages["farewell"], "Bye");
+        assert_eq!(messages["multiline"], "First line\n\nThird line");
+        assert_eq!(messages["not-continued"], "Done");
+    }
+
+    #[test]
+    fn parse_continuation_after_text() {
+        let messages = parse("a = one\n  two\n\nb = three\n");
+        assert_eq!(messages["a"], "one\ntwo");
+        assert_eq!(messages["b"], "three");
+    }
+
+    #[test]
+    fn format_placeholders() {
+        let catalog = Catalog::new(SOURCE, None);
+        assert_eq!(catalog.format("greeting", &[("name", &"easage")]), "Hello, easage!");
+        assert_eq!(catalog.format("greeting", &[("name", &42)]), "Hello, 42!");
+
+        // Placeables without a value, and unbalanced braces, are kept as they are.
+        assert_eq!(catalog.format("greeting", &[]), "Hello, { $name }!");
+        let catalog = Catalog::new("open = a { $b\n", None);
+        assert_eq!(catalog.format("open", &[("b", &1)]), "a { $b");
+    }
+
+    #[test]
+    fn missing_messages() {
+        let catalog = Catalog::new(SOURCE, None);
+        assert_eq!(catalog.text("nope"), "nope");
+        assert_eq!(catalog.format("nope", &[("name", &1)]), "nope");
+    }
+
+    #[test]
+    fn translation_falls_back() {
+        let catalog = Catalog::new(SOURCE, Some("greeting = Hallo, { $name }!\n"));
+        assert_eq!(catalog.format("greeting", &[("name", &"easage")]), "Hallo, easage!");
+        assert_eq!(catalog.text("farewell"), "Bye");
+    }
+
+    #[test]
+    fn english_catalog() {
+        let catalog = Catalog::for_candidates(&candidates(Some("xx_YY.UTF-8")));
+        assert_eq!(catalog.text("app-about"), "Read, create, and unpack from BIG archives");
+        assert_eq!(catalog.format("error", &[("message", &"oops")]), "ERROR: oops");
+    }
+
+    #[test]
+    fn english_has_every_message() {
+        const SOURCES: &[&str] = &[
+            include_str!("digest.rs"),
+            include_str!("easage.rs"),
+            include_str!("easage_bench.rs"),
+            include_str!("easage_cat.rs"),
+            include_str!("easage_compact.rs"),
+            include_str!("easage_completions.rs"),
+            include_str!("easage_hexdump.rs"),
+            include_str!("easage_lint.rs"),
+            include_str!("easage_list.rs"),
+            include_str!("easage_pack.rs"),
+            include_str!("easage_repack.rs"),
+            include_str!("easage_unpack.rs"),
+            include_str!("easage_which.rs"),
+            include_str!("io_args.rs"),
+            include_str!("output.rs"),
+        ];
+
+        let messages = parse(catalog_source(FALLBACK).unwrap());
+        let mut missing = vec![];
+
+        for source in SOURCES {
+            for prefix in &["tr!(\"", "text(\""] {
+                for (start, _) in source.match_indices(prefix) {
+                    let rest = &source[start + prefix.len()..];
+                    let id = &rest[..rest.find('"').unwrap()];
+                    if !messages.contains_key(id) {
+                        missing.push(id.to_string());
+                    }
+                }
+            }
+        }
+
+        // Messages whose ids are built at runtime.
+        let operations = ["allocate", "create", "open", "read", "replace", "write"];
+        let lint_codes = [
+            "mixed-separators", "non-ascii", "control-characters", "leading-separator", "parent-directory",
+            "duplicate", "case-duplicate", "extract-collision", "file-directory-collision",
+        ];
+
+        let ids = operations.iter().map(|operation| format!("io-{}", operation))
+            .chain(lint_codes.iter().map(|code| format!("lint-{}", code)));
+
+        for id in ids {
+            if !messages.contains_key(&id) {
+                missing.push(id);
+            }
+        }
+
+        assert!(missing.is_empty(), "missing from en.ftl: {:?}", missing);
+    }
+
+    #[test]
+    fn candidate_tags() {
+        assert_eq!(candidates(Some("pt_BR.UTF-8")), vec!["pt-BR", "pt"]);
+        assert_eq!(candidates(Some("de_DE@euro:en")), vec!["de-DE", "de", "en"]);
+        assert!(candidates(Some("C")).is_empty());
+    }
+}
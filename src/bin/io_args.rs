@@ -0,0 +1,50 @@
+use clap::{Arg, ArgMatches};
+
+use ::lib::IoOptions;
+use ::parse_number;
+
+pub const ARG_NAME_BUFFER_SIZE: &'static str = "buffer-size";
+pub const ARG_NAME_SEQUENTIAL_HINT: &'static str = "sequential-hint";
+pub const ARG_NAME_PREALLOCATE: &'static str = "preallocate";
+
+pub fn buffer_size_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(ARG_NAME_BUFFER_SIZE)
+        .long(ARG_NAME_BUFFER_SIZE)
+        .value_name("bytes")
+        .takes_value(true)
+        .validator(validate_buffer_size)
+        .help(::i18n::text("io-buffer-size-help"))
+}
+
+pub fn sequential_hint_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(ARG_NAME_SEQUENTIAL_HINT)
+        .long(ARG_NAME_SEQUENTIAL_HINT)
+        .help(::i18n::text("io-sequential-hint-help"))
+}
+
+/// Only for commands that know the size of every file they write up front.
+pub fn preallocate_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name(ARG_NAME_PREALLOCATE)
+        .long(ARG_NAME_PREALLOCATE)
+        .help(::i18n::text("io-preallocate-help"))
+}
+
+/// The options given by whichever of the above the command has.
+pub fn from_args(args: &ArgMatches) -> IoOptions {
+    let buffer_size = args.value_of(ARG_NAME_BUFFER_SIZE)
+        .map(|v| parse_number(v).unwrap() as usize)
+        .unwrap_or(IoOptions::DEFAULT_BUFFER_SIZE);
+
+    IoOptions {
+        buffer_size,
+        sequential: args.is_present(ARG_NAME_SEQUENTIAL_HINT),
+        preallocate: args.is_present(ARG_NAME_PREALLOCATE),
+    }
+}
+
+fn validate_buffer_size(v: String) -> Result<(), String> {
+    match parse_number(&v)? {
+        0 => Err(tr!("invalid-at-least-one", arg = ARG_NAME_BUFFER_SIZE)),
+        _ => Ok(()),
+    }
+}
@@ -0,0 +1,235 @@
+# The English messages of the easage command line.
+#
+# Every other catalog falls back to these, so a translation may leave out any
+# message. See "Translations" in the README.
+
+## Shared
+
+error = ERROR: { $message }
+error-in = ERROR: { $path }: { $message }
+warning-custom-magic = WARNING: writing custom magic { $magic }; the games and most tools only read BIG4 and BIGF archives
+replacing-existing-file = , replacing the existing file
+plan-header = Would write { $path } ({ $count } entries, { $size } bytes{ $replacing })
+plan-entry = { $name } ({ $size } bytes)
+lang-help = the language of messages (default: from EASAGE_LANG, LC_ALL, LC_MESSAGES, or LANG)
+
+## Commands
+
+app-about = Read, create, and unpack from BIG archives
+bench-about = Measure how fast this machine parses, extracts, and packs BIG archives
+cat-about = Write the data of a single entry to stdout
+compact-about = Rewrite a BIG archive without the unused space between its entries
+completions-about = Generate tab-completion scripts (prints to stdout)
+hexdump-about = Print an offset, hex, and ASCII dump of part of a BIG archive
+lint-about = Report entry names that are likely to cause problems when extracted or loaded
+list-about = List the contents of a BIG archive
+pack-about = Recursively package a directory structure into a BIG archive
+repack-about = Rewrite an existing BIG archive with normalized settings
+unpack-about = Unpack the contents of a BIG archive
+which-about = Find which BIG archives in a directory provide an entry and which one wins
+
+## bench
+
+bench-environment = easage { $version } on { $os } { $arch } ({ $cpus } CPUs)
+bench-io = buffer size: { $buffer_size }, sequential hint: { $sequential }, preallocate: { $preallocate }
+bench-result = { $name } { $runs } runs, min { $min }, median { $median }, max { $max }, { $throughput } MiB/s
+bench-table-parse = table parse
+bench-extract = extract
+bench-pack = pack
+
+## compact
+
+compact-gap = { $start }..{ $end } ({ $len } bytes)
+compact-plan = { $path } has { $slack } unused bytes in { $gaps } ranges; compacting would reclaim { $reclaimed } bytes ({ $before } -> { $after })
+compact-reclaimed = Reclaimed { $reclaimed } bytes ({ $before } -> { $after })
+
+## list
+
+list-archive = Archive:
+list-kind = kind: { $kind }
+list-size = size: { $size }
+list-len = len: { $len }
+list-secret-data = secret data: { $data }
+list-secret-data-len = secret data len: { $len }
+list-data-start = data start: { $offset }
+list-entries = Entries:
+list-offset = offset: { $offset }
+list-index = index: { $index }
+list-no-duplicates = No entry is provided by more than one of the { $count } archives in { $directory }
+list-duplicate-wins = wins { $path } (len: { $len })
+list-duplicate-shadowed = shadowed { $path } (len: { $len })
+list-duplicates = { $count } entries are provided by more than one archive
+
+## pack
+
+pack-packed = Packed { $directory } into { $path }
+pack-batch-failed = Failed to pack { $failed } of { $total } directories
+pack-unexpected-order =
+    Unexpected error!
+    Please file a bug at https://github.com/Phrohdoh/easage/issues/new and provide the following text:
+
+    Invalid input to 'arg_order_to_enum': { $input }
+    Did you validate input via 'validate_order'?
+
+## unpack
+
+unpack-skipped-resume = Skipped { $count } already extracted entries
+unpack-skipped-existing = Skipped { $count } entries whose output file already exists
+unpack-backed-up = Backed up existing file to { $path }
+unpack-directories = Created { $count } directories from directory entries
+unpack-renamed = Extracted { $name } to { $path } as its path differs from another entry's only by case
+
+## which
+
+which-provider = { $marker } { $path } (offset: { $offset }, len: { $len })
+
+## Errors
+
+error-pack = Failed to pack the given directory: { $message }
+io-allocate = Failed to allocate space for '{ $path }': { $message }
+io-create = Failed to create '{ $path }': { $message }
+io-open = Failed to open '{ $path }': { $message }
+io-read = Failed to read '{ $path }': { $message }
+io-replace = Failed to replace '{ $path }': { $message }
+io-write = Failed to write '{ $path }': { $message }
+no-entry-named = No entry named { $name } in { $path }
+no-entry-at-index = No entry at index { $index } in { $path }
+no-entry-at-offset = No entry contains offset { $offset } in { $path }
+hexdump-range-past-end = Range { $start }..{ $end } extends past the end of the { $len } byte archive
+lint-found = Found { $count } problematic entry names in { $path }
+pack-no-inherit-source = '{ $path }' does not exist so there is no archive to inherit settings from (pass an explicit --kind to create it)
+which-not-found = No archive in { $directory } provides { $entry }
+
+## Invalid arguments
+
+invalid-path-metadata = Unable to read metadata to validate path. Are you sure this file exists?
+invalid-path-not-file = path must be an existing file (not a directory)
+invalid-number = '{ $value }' is not a valid decimal or 0x-prefixed hexadecimal number
+invalid-kind = '{ $value }' is not BIGF, BIG4, or 4 ASCII characters of custom magic
+invalid-at-least-one = { $arg } must be at least 1
+invalid-positive = { $arg } must be a number greater than 0
+invalid-one-of = { $arg } must be one of '{ $first }' or '{ $second }'
+invalid-range = '{ $value }' is not a range, expected START..END
+invalid-range-order = range start { $start } is greater than range end { $end }
+
+## Library errors
+
+lib-path-not-found = Unable to find the path '{ $path }'. Perhaps it does not exist or you do not have the required permissions.
+lib-create-empty = Unable to create an empty archive.
+lib-incomplete-archive =
+    Failed to read data from an incomplete archive.
+    Archive is { $actual_len } bytes long but was expected to be at least { $expected_len }.
+    Attempted to read from offset { $start } to { $end } inclusive.
+lib-corrupt-table = The entry metadata table is corrupt at record #{ $index } (offset { $offset }): { $reason }
+lib-no-such-entry = The entry '{ $name }' does not exist in this archive.
+lib-entry-out-of-bounds =
+    The data of entry '{ $name }' lies outside of the archive.
+    Archive is { $archive_len } bytes long but the entry spans offset { $start } to { $end } (exclusive).
+lib-destination-exists = Refusing to overwrite '{ $path }' which already exists.
+lib-unsafe-entry-name = Refusing to extract entry '{ $name }' as it would be written outside of the output directory.
+lib-directory-marker-with-data = Entry '{ $name }' names a directory but has { $len } bytes of data.
+lib-case-collision = Entries '{ $first }' and '{ $second }' would be extracted to the same file on a case-insensitive filesystem.
+lib-transform-failed = Failed to transform entry '{ $name }': { $message }
+lib-duplicate-entry = More than one entry is named '{ $name }'.
+lib-archive-too-large = The archive would be { $size } bytes long which exceeds the maximum BIG size of { $max } bytes.
+lib-too-many-entries = The archive has { $count } entries which exceeds the limit of { $max }.
+lib-entry-too-large = Entry '{ $name }' is { $size } bytes long which exceeds the limit of { $max } bytes.
+lib-total-size-too-large = The entries total { $size } bytes which exceeds the limit of { $max } bytes.
+lib-name-decoding = Failed to decode the name of entry #{ $index } at offset { $offset }: { $reason }
+lib-io = I/O error while { $operation }: { $message }
+lib-invalid-magic = The data provided { $magic } is neither BIG4 nor BIGF.
+lib-unsupported-format = Archives with magic { $magic } can be read but not written; convert them to BIG4 or BIGF instead.
+
+## lint
+
+lint-mixed-separators = { $names } uses both '\' and '/' as separators
+lint-non-ascii = { $names } contains non-ASCII characters
+lint-control-characters = { $names } contains control characters
+lint-leading-separator = { $names } starts with a separator
+lint-parent-directory = { $names } contains a '..' component
+lint-duplicate = { $names } is the name of { $count } records
+lint-case-duplicate = { $names } differ only by case
+lint-extract-collision = { $names } collide when extracted to a case-insensitive filesystem
+lint-file-directory-collision = { $names } need the same path to be both a file and a directory
+
+## Options
+
+digest-print-digest-help = print the SHA-256 of the written archive in the format used by 'sha256sum'
+
+bench-source-help = a BIG archive to measure parsing its table and extracting all of its entries with
+bench-directory-help = a directory to measure packing into a BIG archive with
+bench-iterations-help = how many times to run each benchmark (default: 5)
+bench-scratch-help = the directory to extract and pack into, which should be on the disk being measured (default: the system temporary directory)
+
+cat-source-help = path to the BIG to read
+cat-entry-help = name of the entry
+cat-index-help = position of the entry in the archive's table (0-based)
+cat-offset-help = an archive offset (decimal or 0x-prefixed hex) inside of the entry's data
+
+compact-source-help = path to the BIG archive to compact
+compact-output-help = path to write the compacted archive to (default: replace --source)
+compact-dry-run-help = print the unused byte ranges and how much compacting would reclaim without writing anything
+
+completions-shell-help = the shell to generate the script for
+completions-list-entries-help = print the entry names of the given BIG archive, one per line (for use by completion scripts)
+
+hexdump-source-help = path to the BIG to read
+hexdump-entry-help = dump the data of the named entry
+hexdump-range-help = dump the given (end-exclusive) range of archive offsets, decimal or 0x-prefixed hex
+
+lint-source-help = path to the BIG to check
+
+list-source-help = path to the BIG to read
+list-verbose-help = if supplied output more information (typically only useful for developing easage itself)
+list-lenient-magic-help = read archives whose magic is neither BIG4 nor BIGF (e.g. from modified engines)
+list-duplicates-across-help = instead of listing one archive, list the entries provided by more than one BIG archive in this directory and which one the game uses
+
+pack-source-help = path to the directory to pack into a BIG archive
+pack-output-help = path to the output BIG archive (or directory, with --batch)
+pack-kind-help = use BIG4 for the Battle for Middle-Earth series or BIGF for Generals / Zero-Hour (any other 4 characters are written as custom magic), or 'auto' to keep the kind of the existing archive at --output
+pack-strip-prefix-help = a prefix to strip from entry names
+pack-order-help = criteria used to determine entry order in the archive
+pack-store-metadata-help = store file modification times and permissions in the archive so unpack can restore them
+pack-checksums-help = store the CRC-32 of every entry in the archive so corruption can be detected (ignored by the games)
+pack-directory-markers-help = add an empty entry ending in a separator for every directory so empty directories are kept
+pack-batch-help = pack each immediate subdirectory of --source into its own <output>/<name>.big (entry names are relative to the subdirectory)
+pack-jobs-help = with --batch, the number of archives to pack in parallel (default: 1)
+pack-separator-help = the separator used in entry names (the same on every platform)
+pack-inherit-order-help = write entries that are in the existing archive at --output in the same order, followed by new entries in --order
+pack-inherit-secret-data-help = copy the secret data of the existing archive at --output (metadata and checksums are regenerated, not copied)
+pack-dry-run-help = print the entries that would be written and the resulting archive size without writing anything
+
+repack-source-help = path to the BIG archive to repack
+repack-output-help = path to the output BIG archive (may be the same as --source)
+repack-kind-help = convert to BIGF, BIG4, or any other 4 characters of custom magic (default: keep the source's kind)
+repack-order-help = reorder entries by this criteria (default: keep the source's order)
+repack-separator-help = rewrite every separator in entry names to this one
+repack-deduplicate-help = store identical entry data only once
+repack-align-help = start the data of every entry at a multiple of this many bytes (decimal or 0x-prefixed hex)
+repack-strip-secret-data-help = drop the data between the entry table and the first entry
+repack-dry-run-help = print the entries that would be written and the resulting archive size without writing anything
+
+unpack-source-help = path to the BIG archive to unpack files from
+unpack-output-help = path to the directory to write files to
+unpack-names-help = one or more entry names to extract
+unpack-all-help = unpack all entries
+unpack-ignore-metadata-help = do not restore file modification times and permissions stored by 'pack --store-metadata'
+unpack-resume-help = skip entries whose output file already exists with the same size (continue an interrupted unpack)
+unpack-checksum-help = with --resume, also compare the contents of existing files before skipping them
+unpack-overwrite-help = replace files that already exist in the output directory
+unpack-skip-existing-help = leave files that already exist in the output directory alone
+unpack-backup-help = rename files that already exist in the output directory to '<name>.bak' before extracting
+unpack-flatten-help = write every entry directly into the output directory, renaming entries whose file names collide to 'name (N).ext'
+unpack-prefix-help = a directory, relative to the output directory, to write entries into
+unpack-case-collisions-help = what to do with entries whose paths differ only by case (default: fail on Windows and macOS, allow elsewhere)
+
+which-directory-help = path to the game directory containing BIG archives
+which-entry-help = entry name to look for (case-insensitive, may contain '*' and '?')
+which-recursive-help = also search for BIG archives in subdirectories
+
+io-buffer-size-help = the size of the output buffer (decimal or 0x-prefixed hex, default: 8192)
+io-sequential-hint-help = tell the operating system files will be read sequentially so it reads ahead further (ignored where unsupported)
+io-preallocate-help = reserve the final size of every written file before writing it (ignored where unsupported)
+
+output-format-help = output format (json and csv are intended for scripts)
+output-quiet-help = print nothing to stdout, only report success via the exit code
@@ -0,0 +1,260 @@
+use clap::{Arg, ArgMatches};
+
+pub const ARG_NAME_FORMAT: &'static str = "format";
+pub const ARG_NAME_QUIET: &'static str = "quiet";
+
+const ARG_VALUE_FORMAT_TEXT: &'static str = "text";
+const ARG_VALUE_FORMAT_JSON: &'static str = "json";
+const ARG_VALUE_FORMAT_CSV: &'static str = "csv";
+
+// NOTE: A command that completes (and, if applicable, finds what it was
+// looking for) exits with 0.
+
+/// The command could not complete (bad input, I/O failure, corrupt archive, etc).
+pub const EXIT_ERROR: i32 = 1;
+
+/// The command completed but a query matched nothing.
+pub const EXIT_NOT_FOUND: i32 = 2;
+
+/// The command completed and a check (such as `lint`) found problems.
+pub const EXIT_CHECK_FAILED: i32 = 3;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Format {
+    Text,
+    Json,
+    Csv,
+}
+
+/// A single cell of structured output.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Str(String),
+    Num(u64),
+    Bool(bool),
+
+    /// A JSON array, or in CSV a field holding the items as a CSV record.
+    List(Vec<String>),
+}
+
+impl<'a> From<&'a str> for Value {
+    fn from(s: &'a str) -> Self {
+        Value::Str(s.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(s: String) -> Self {
+        Value::Str(s)
+    }
+}
+
+impl From<Vec<String>> for Value {
+    fn from(items: Vec<String>) -> Self {
+        Value::List(items)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(n: u32) -> Self {
+        Value::Num(u64::from(n))
+    }
+}
+
+impl From<u64> for Value {
+    fn from(n: u64) -> Self {
+        Value::Num(n)
+    }
+}
+
+impl From<bool> for Value {
+    fn from(b: bool) -> Self {
+        Value::Bool(b)
+    }
+}
+
+/// The `--format` and `--quiet` arguments shared by all subcommands that print results.
+pub fn args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name(ARG_NAME_FORMAT)
+            .long(ARG_NAME_FORMAT)
+            .value_name(ARG_NAME_FORMAT)
+            .takes_value(true)
+            .default_value(ARG_VALUE_FORMAT_TEXT)
+            .possible_values(&[ARG_VALUE_FORMAT_TEXT, ARG_VALUE_FORMAT_JSON, ARG_VALUE_FORMAT_CSV])
+            .help(::i18n::text("output-format-help")),
+        Arg::with_name(ARG_NAME_QUIET)
+            .long(ARG_NAME_QUIET)
+            .short("q")
+            .help(::i18n::text("output-quiet-help")),
+    ]
+}
+
+/// Where and how a subcommand should print its results.
+#[derive(Debug, Copy, Clone)]
+pub struct Output {
+    pub format: Format,
+    pub quiet: bool,
+}
+
+impl Output {
+    pub fn from_args(args: &ArgMatches) -> Self {
+        let format = match args.value_of(ARG_NAME_FORMAT) {
+            Some(ARG_VALUE_FORMAT_JSON) => Format::Json,
+            Some(ARG_VALUE_FORMAT_CSV) => Format::Csv,
+            _ => Format::Text,
+        };
+
+        Output {
+            format,
+            quiet: args.is_present(ARG_NAME_QUIET),
+        }
+    }
+
+    /// Whether the caller should print its own human-readable output.
+    pub fn is_text(&self) -> bool {
+        !self.quiet && self.format == Format::Text
+    }
+
+    /// Print `rows` (one `Value` per column) as a JSON array of objects or as CSV.
+    ///
+    /// Does nothing when quiet or when the format is `Text`.
+    pub fn print_rows(&self, columns: &[&str], rows: &[Vec<Value>]) {
+        if self.quiet {
+            return;
+        }
+
+        match self.format {
+            Format::Text => {},
+            Format::Json => println!("{}", to_json(columns, rows)),
+            Format::Csv => print!("{}", to_csv(columns, rows)),
+        }
+    }
+}
+
+fn to_json(columns: &[&str], rows: &[Vec<Value>]) -> String {
+    let objects = rows.iter()
+        .map(|row| {
+            let fields = columns.iter().zip(row)
+                .map(|(column, value)| format!("{}:{}", json_string(column), json_value(value)))
+                .collect::<Vec<_>>();
+
+            format!("{{{}}}", fields.join(","))
+        })
+        .collect::<Vec<_>>();
+
+    format!("[{}]", objects.join(","))
+}
+
+fn json_value(value: &Value) -> String {
+    match *value {
+        Value::Str(ref s) => json_string(s),
+        Value::Num(n) => n.to_string(),
+        Value::Bool(b) => b.to_string(),
+        Value::List(ref items) => {
+            let items = items.iter().map(|item| json_string(item)).collect::<Vec<_>>();
+            format!("[{}]", items.join(","))
+        },
+    }
+}
+
+fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+
+    out.push('"');
+    out
+}
+
+fn to_csv(columns: &[&str], rows: &[Vec<Value>]) -> String {
+    let mut out = columns.iter()
+        .map(|c| csv_field(c))
+        .collect::<Vec<_>>()
+        .join(",");
+    out.push('\n');
+
+    for row in rows {
+        let fields = row.iter()
+            .map(|value| match *value {
+                Value::Str(ref s) => csv_field(s),
+                Value::Num(n) => n.to_string(),
+                Value::Bool(b) => b.to_string(),
+                Value::List(ref items) => {
+                    let items = items.iter().map(|item| csv_field(item)).collect::<Vec<_>>();
+                    csv_field(&items.join(","))
+                },
+            })
+            .collect::<Vec<_>>();
+
+        out.push_str(&fields.join(","));
+        out.push('\n');
+    }
+
+    out
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(json_string(r#"say "hi""#), r#""say \"hi\"""#);
+        assert_eq!(json_string(r"Data\INI\a.ini"), r#""Data\\INI\\a.ini""#);
+        assert_eq!(json_string("a\nb\r\tc\u{0}\u{1f}"), r#""a\nb\r\tc\u0000\u001f""#);
+        assert_eq!(json_string("Données\\é.ini"), r#""Données\\é.ini""#);
+    }
+
+    #[test]
+    fn json_rows() {
+        let rows = vec![vec![Value::from(r#"a"\b"#), Value::from(3u32), Value::from(true)]];
+        assert_eq!(to_json(&["name", "len", "ok"], &rows), r#"[{"name":"a\"\\b","len":3,"ok":true}]"#);
+        assert_eq!(to_json(&["name"], &[]), "[]");
+
+        let rows = vec![vec![Value::from(vec!["a|b".to_string(), r#"c"d"#.to_string()])], vec![Value::from(vec![])]];
+        assert_eq!(to_json(&["names"], &rows), r#"[{"names":["a|b","c\"d"]},{"names":[]}]"#);
+    }
+
+    #[test]
+    fn csv_escaping() {
+        assert_eq!(csv_field(r"Data\INI\a.ini"), r"Data\INI\a.ini");
+        assert_eq!(csv_field("a,b"), r#""a,b""#);
+        assert_eq!(csv_field(r#"say "hi""#), r#""say ""hi""""#);
+        assert_eq!(csv_field("a\nb"), "\"a\nb\"");
+        assert_eq!(csv_field("a\rb"), "\"a\rb\"");
+        assert_eq!(csv_field("é.ini"), "é.ini");
+    }
+
+    #[test]
+    fn csv_rows() {
+        let rows = vec![
+            vec![Value::from("a,b"), Value::from(1u64), Value::from(false)],
+            vec![Value::from("line\nbreak"), Value::from(2u64), Value::from(true)],
+        ];
+
+        assert_eq!(to_csv(&["name", "len", "ok"], &rows), "name,len,ok\n\"a,b\",1,false\n\"line\nbreak\",2,true\n");
+
+        let rows = vec![vec![Value::from(vec!["a".to_string(), "b,c".to_string()])]];
+        assert_eq!(to_csv(&["names"], &rows), "names\n\"a,\"\"b,c\"\"\"\n");
+    }
+}
@@ -0,0 +1,121 @@
+//! Per-entry CRC-32 checksums stored in an archive's secret data.
+//!
+//! BIG archives have no integrity checks of their own. When requested the
+//! packer stores a `Checksums` table in the secret data (which the games
+//! ignore) so `Archive::validate_with(.., Level::WithChecksums)` can later
+//! detect corrupted or modified entries.
+
+use std::collections::HashMap;
+
+use ::crc32;
+use ::secret_data;
+
+/// The CRC-32 of every entry in an archive, keyed by entry name.
+///
+/// Serialized as UTF-8 lines of `crc<TAB>name` where `crc` is 8 lowercase
+/// hexadecimal digits.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Checksums {
+    entries: HashMap<String, u32>,
+}
+
+impl Checksums {
+    /// The `secret_data` section tag checksums are stored under.
+    pub const SECTION_TAG: [u8; 4] = *b"EZCK";
+
+    pub fn new() -> Self {
+        Checksums::default()
+    }
+
+    /// Compute the checksums of the given `(name, data)` pairs.
+    pub fn compute<'a, I>(entries: I) -> Self
+        where I: IntoIterator<Item = (&'a str, &'a [u8])> {
+        let mut checksums = Checksums::new();
+        for (name, data) in entries {
+            checksums.insert(name.to_string(), crc32::checksum(data));
+        }
+
+        checksums
+    }
+
+    pub fn insert(&mut self, name: String, crc: u32) {
+        self.entries.insert(name, crc);
+    }
+
+    pub fn get(&self, name: &str) -> Option<u32> {
+        self.entries.get(name).cloned()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Find and parse checksums in an archive's secret data.
+    pub fn from_secret_data(data: &[u8]) -> Option<Checksums> {
+        secret_data::find_section(data, &Self::SECTION_TAG)
+            .and_then(Checksums::from_bytes)
+    }
+
+    /// Parse serialized checksums, returning `None` if they are malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Checksums> {
+        let text = ::std::str::from_utf8(bytes).ok()?;
+        let mut checksums = Checksums::new();
+
+        for line in text.lines() {
+            let mut parts = line.splitn(2, '\t');
+            let crc = u32::from_str_radix(parts.next()?, 16).ok()?;
+            let name = parts.next()?;
+
+            checksums.insert(name.to_string(), crc);
+        }
+
+        Some(checksums)
+    }
+
+    /// Serialize these checksums, sorted by entry name so output is reproducible.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut names = self.entries.keys().collect::<Vec<_>>();
+        names.sort();
+
+        let mut text = String::new();
+        for name in names {
+            text.push_str(&format!("{:08x}\t{}\n", self.entries[name], name));
+        }
+
+        text.into_bytes()
+    }
+
+    /// The length of `to_bytes` for entries with the given names, which does
+    /// not depend on their data.
+    pub fn serialized_len<'a, I>(names: I) -> usize
+        where I: IntoIterator<Item = &'a str> {
+        names.into_iter()
+            .map(|name| 8 + 1 + name.len() + 1)
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksums_round_trip() {
+        let checksums = Checksums::compute(vec![("b.ini", &b"123456789"[..]), ("a\\x.ini", &b""[..])]);
+
+        let bytes = checksums.to_bytes();
+        assert_eq!(bytes, b"00000000\ta\\x.ini\ncbf43926\tb.ini\n".to_vec());
+        assert_eq!(bytes.len(), Checksums::serialized_len(vec!["b.ini", "a\\x.ini"]));
+        assert_eq!(Checksums::from_bytes(&bytes), Some(checksums));
+    }
+
+    #[test]
+    fn checksums_malformed() {
+        assert_eq!(Checksums::from_bytes(b"nothex\tname\n"), None);
+        assert_eq!(Checksums::from_bytes(b"00000000\n"), None);
+    }
+}
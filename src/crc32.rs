@@ -0,0 +1,113 @@
+//! The CRC-32 (IEEE 802.3) checksum used by zip, PNG, and friends, and the
+//! other CRC-32 variants engines hash entry names with (see `hash_index`).
+//!
+//! This is not a cryptographic hash. It is only used to cheaply detect
+//! changed or corrupted data.
+//!
+//! Variants are named as in the CRC RevEng catalogue.
+
+/// The CRC-32 polynomial, reversed for reflected (least significant bit first) CRCs.
+const POLYNOMIAL: u32 = 0xEDB8_8320;
+
+/// The CRC-32C (Castagnoli) polynomial, reversed.
+const POLYNOMIAL_CASTAGNOLI: u32 = 0x82F6_3B78;
+
+/// The CRC-32 polynomial in its normal form for unreflected (most significant bit first) CRCs.
+const POLYNOMIAL_NORMAL: u32 = 0x04C1_1DB7;
+
+const TABLE: [u32; 256] = make_table(POLYNOMIAL);
+const TABLE_CASTAGNOLI: [u32; 256] = make_table(POLYNOMIAL_CASTAGNOLI);
+const TABLE_NORMAL: [u32; 256] = make_table_normal(POLYNOMIAL_NORMAL);
+
+const fn make_table(polynomial: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 1 == 1 { (crc >> 1) ^ polynomial } else { crc >> 1 };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+const fn make_table_normal(polynomial: u32) -> [u32; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+
+    while i < 256 {
+        let mut crc = (i as u32) << 24;
+        let mut bit = 0;
+
+        while bit < 8 {
+            crc = if crc & 0x8000_0000 != 0 { (crc << 1) ^ polynomial } else { crc << 1 };
+            bit += 1;
+        }
+
+        table[i] = crc;
+        i += 1;
+    }
+
+    table
+}
+
+fn update(table: &[u32; 256], crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &b| table[((crc ^ u32::from(b)) & 0xFF) as usize] ^ (crc >> 8))
+}
+
+fn update_normal(table: &[u32; 256], crc: u32, data: &[u8]) -> u32 {
+    data.iter().fold(crc, |crc, &b| table[(((crc >> 24) ^ u32::from(b)) & 0xFF) as usize] ^ (crc << 8))
+}
+
+/// Compute the CRC-32 (CRC-32/ISO-HDLC) of `data`.
+pub fn checksum(data: &[u8]) -> u32 {
+    !update(&TABLE, !0, data)
+}
+
+/// CRC-32/JAMCRC: `checksum` without the final inversion.
+pub fn jamcrc(data: &[u8]) -> u32 {
+    update(&TABLE, !0, data)
+}
+
+/// CRC-32C (CRC-32/ISCSI), with the Castagnoli polynomial.
+pub fn castagnoli(data: &[u8]) -> u32 {
+    !update(&TABLE_CASTAGNOLI, !0, data)
+}
+
+/// CRC-32/BZIP2: the CRC-32 polynomial without reflection.
+pub fn bzip2(data: &[u8]) -> u32 {
+    !update_normal(&TABLE_NORMAL, !0, data)
+}
+
+/// CRC-32/MPEG-2: `bzip2` without the final inversion.
+pub fn mpeg2(data: &[u8]) -> u32 {
+    update_normal(&TABLE_NORMAL, !0, data)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn checksum_known_values() {
+        assert_eq!(checksum(b""), 0);
+        assert_eq!(checksum(b"123456789"), 0xCBF4_3926);
+        assert_eq!(checksum(b"The quick brown fox jumps over the lazy dog"), 0x414F_A339);
+    }
+
+    #[test]
+    fn variants_known_values() {
+        assert_eq!(jamcrc(b"123456789"), 0x340B_C6D9);
+        assert_eq!(castagnoli(b"123456789"), 0xE306_9283);
+        assert_eq!(bzip2(b"123456789"), 0xFC89_1918);
+        assert_eq!(mpeg2(b"123456789"), 0x0376_E6E7);
+    }
+}
@@ -1,4 +1,5 @@
 use ::std::io;
+use ::std::path::Path;
 use ::std::result;
 
 #[derive(Debug, Fail)]
@@ -21,13 +22,100 @@ Attempted to read from offset {:#X} to {:#X} inclusive.", actual_len, expected_l
         read_end: usize,
     },
 
-    #[fail(display = "The requested entry does not exist in this archive.")]
-    NoSuchEntry,
+    #[fail(display = "The entry metadata table is corrupt at record #{} (offset {:#X}): {}", index, offset, reason)]
+    CorruptTable {
+        index: usize,
+        offset: usize,
+        reason: String,
+    },
+
+    #[fail(display = "The entry '{}' does not exist in this archive.", name)]
+    NoSuchEntry {
+        name: String,
+    },
+
+    #[fail(display = "The data of entry '{}' lies outside of the archive.
+Archive is {} bytes long but the entry spans offset {:#X} to {:#X} (exclusive).", name, archive_len, start, end)]
+    EntryOutOfBounds {
+        name: String,
+        start: usize,
+        end: usize,
+        archive_len: usize,
+    },
+
+    #[fail(display = "Refusing to overwrite '{}' which already exists.", path)]
+    DestinationExists {
+        path: String,
+    },
+
+    #[fail(display = "Refusing to extract entry '{}' as it would be written outside of the output directory.", name)]
+    UnsafeEntryName {
+        name: String,
+    },
+
+    #[fail(display = "Entry '{}' names a directory but has {} bytes of data.", name, len)]
+    DirectoryMarkerWithData {
+        name: String,
+        len: u32,
+    },
+
+    #[fail(display = "Entries '{}' and '{}' would be extracted to the same file on a case-insensitive filesystem.", first, second)]
+    CaseCollision {
+        first: String,
+        second: String,
+    },
+
+    #[fail(display = "Failed to transform entry '{}': {}", name, inner)]
+    TransformFailed {
+        #[cause]
+        inner: io::Error,
+
+        name: String,
+    },
+
+    #[fail(display = "More than one entry is named '{}'.", name)]
+    DuplicateEntry {
+        name: String,
+    },
+
+    #[fail(display = "The archive would be {} bytes long which exceeds the maximum BIG size of {} bytes.", size, max_size)]
+    ArchiveTooLarge {
+        size: u64,
+        max_size: u64,
+    },
+
+    #[fail(display = "The archive has {} entries which exceeds the limit of {}.", count, max)]
+    TooManyEntries {
+        count: usize,
+        max: usize,
+    },
+
+    #[fail(display = "Entry '{}' is {} bytes long which exceeds the limit of {} bytes.", name, size, max)]
+    EntryTooLarge {
+        name: String,
+        size: u64,
+        max: u64,
+    },
 
-    #[fail(display = "I/O error: {}", inner)]
+    #[fail(display = "The entries total {} bytes which exceeds the limit of {} bytes.", size, max)]
+    TotalSizeTooLarge {
+        size: u64,
+        max: u64,
+    },
+
+    #[fail(display = "Failed to decode the name of entry #{} at offset {:#X}: {}", index, offset, reason)]
+    NameDecoding {
+        index: usize,
+        offset: usize,
+        reason: String,
+    },
+
+    #[fail(display = "I/O error while {}: {}", operation, inner)]
     IO {
         #[cause]
-        inner: io::Error
+        inner: io::Error,
+
+        operation: String,
     },
 
     #[fail(display = "The data provided {:?} is neither BIG4 nor BIGF.", magic)]
@@ -35,16 +123,25 @@ Attempted to read from offset {:#X} to {:#X} inclusive.", actual_len, expected_l
         magic: Vec<u8>,
     },
 
+    #[fail(display = "Archives with magic {:?} can be read but not written; convert them to BIG4 or BIGF instead.", magic)]
+    UnsupportedFormat {
+        magic: Vec<u8>,
+    },
+
     #[fail(display = "{}", message)]
     Custom {
         message: String,
     },
 }
 
-impl From<io::Error> for Error {
-    fn from(e: io::Error) -> Self {
+impl Error {
+    /// Wrap an `io::Error` that occurred while performing `operation` on `path`.
+    ///
+    /// `operation` should read naturally after "while", e.g. `"opening"`.
+    pub fn io<P: AsRef<Path>>(inner: io::Error, operation: &str, path: P) -> Self {
         Error::IO {
-            inner: e,
+            inner,
+            operation: format!("{} '{}'", operation, path.as_ref().display()),
         }
     }
 }
@@ -59,4 +156,4 @@ impl From<::walkdir::Error> for Error {
     }
 }
 
-pub type Result<T> = result::Result<T, Error>;
\ No newline at end of file
+pub type Result<T> = result::Result<T, Error>;
@@ -0,0 +1,935 @@
+//! Writing entries from an archive to the filesystem.
+//!
+//! This is the code behind `easage unpack` so that every front-end extracts
+//! entries the same way.
+
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Write};
+use std::path::{Component, Path, PathBuf};
+use std::sync::Arc;
+
+use ::{Archive, EntryInfo, EntryInfoTable, Error, IoOptions, Result};
+use ::crc32;
+use ::metadata::Manifest;
+
+/// How to decide that an existing destination file is already extracted.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Resume {
+    /// Always write every entry.
+    Disabled,
+
+    /// Skip entries whose destination file exists with the same size.
+    MatchingSize,
+
+    /// Skip entries whose destination file exists with the same size and CRC-32.
+    MatchingChecksum,
+}
+
+/// What to do when an entry's destination file already exists.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum OnConflict {
+    /// Return `Error::DestinationExists` before anything is written.
+    Fail,
+
+    /// Replace the existing file.
+    Overwrite,
+
+    /// Leave the existing file alone and do not extract the entry.
+    Skip,
+
+    /// Rename the existing file (see `backup_path`) then extract the entry.
+    Backup,
+}
+
+/// What to do when two entries would be extracted to paths that differ only
+/// by case, which are the same file on a case-insensitive filesystem.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CaseCollision {
+    /// Extract both (on a case-insensitive filesystem the later entry wins).
+    Allow,
+
+    /// Return `Error::CaseCollision` before anything is written.
+    Fail,
+
+    /// Extract the later entry (in table order) to `stem (N).ext` instead.
+    Rename,
+}
+
+impl CaseCollision {
+    /// `Fail` on Windows and macOS (whose filesystems are case-insensitive by
+    /// default) and `Allow` elsewhere.
+    pub fn platform_default() -> Self {
+        if cfg!(any(windows, target_os = "macos")) {
+            CaseCollision::Fail
+        } else {
+            CaseCollision::Allow
+        }
+    }
+}
+
+/// Called with an entry's name and data, returns the data to write instead.
+pub type TransformFn = dyn Fn(&str, &[u8]) -> io::Result<Vec<u8>> + Send + Sync;
+
+/// Rewrites the data of matching entries before they are written to disk
+/// (to convert an image or rewrite paths in an INI, for example).
+///
+/// ```
+/// use easage::extract::Transform;
+///
+/// let uppercase = Transform::new(
+///     |name| name.to_lowercase().ends_with(".ini"),
+///     |_name, data| Ok(data.to_ascii_uppercase()));
+///
+/// assert!(uppercase.matches("Data\\INI\\GameData.ini"));
+/// assert!(!uppercase.matches("Art\\a.tga"));
+/// ```
+#[derive(Clone)]
+pub struct Transform {
+    filter: Arc<dyn Fn(&str) -> bool + Send + Sync>,
+    apply: Arc<TransformFn>,
+    extension: Option<String>,
+}
+
+impl Transform {
+    /// Pass the name and data of every entry for which `filter` returns
+    /// `true` to `apply`, and write what it returns instead of the data.
+    pub fn new<F, A>(filter: F, apply: A) -> Self
+        where F: Fn(&str) -> bool + Send + Sync + 'static,
+              A: Fn(&str, &[u8]) -> io::Result<Vec<u8>> + Send + Sync + 'static {
+        Transform {
+            filter: Arc::new(filter),
+            apply: Arc::new(apply),
+            extension: None,
+        }
+    }
+
+    /// Replace the extension of the files transformed entries are written to
+    /// (`Art\\a.tga` becomes `Art/a.png` with `"png"`).
+    pub fn with_extension<S: Into<String>>(mut self, extension: S) -> Self {
+        self.extension = Some(extension.into());
+        self
+    }
+
+    /// Whether this transform applies to the entry named `name`.
+    pub fn matches(&self, name: &str) -> bool {
+        (self.filter)(name)
+    }
+}
+
+impl fmt::Debug for Transform {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Transform")
+            .field("extension", &self.extension)
+            .finish()
+    }
+}
+
+/// Options that control `extract` and `extract_all`.
+#[derive(Debug, Clone)]
+pub struct ExtractOptions {
+    /// Skip entries that a previous (interrupted) extraction already wrote.
+    ///
+    /// Existing files that do not match are then subject to `on_conflict`.
+    pub resume: Resume,
+
+    /// What to do with existing destination files.
+    pub on_conflict: OnConflict,
+
+    /// Restore modification times and permissions stored by `packer::Settings::store_metadata`.
+    pub restore_metadata: bool,
+
+    /// Write every entry directly into the output directory, dropping the
+    /// directories in its name (see `flatten_name`).
+    pub flatten: bool,
+
+    /// A directory (relative to the output directory) to write entries into.
+    pub prefix: Option<PathBuf>,
+
+    /// Applied to entry data before it is written. Only the first transform
+    /// that matches an entry is used.
+    ///
+    /// With `resume` an existing file is compared to the transformed data.
+    pub transforms: Vec<Transform>,
+
+    /// What to do with entries whose paths differ only by case.
+    pub on_case_collision: CaseCollision,
+
+    /// How the archive is read and files are written.
+    pub io: IoOptions,
+}
+
+impl Default for ExtractOptions {
+    fn default() -> Self {
+        ExtractOptions {
+            resume: Resume::Disabled,
+            on_conflict: OnConflict::Fail,
+            restore_metadata: true,
+            flatten: false,
+            prefix: None,
+            transforms: vec![],
+            on_case_collision: CaseCollision::platform_default(),
+            io: IoOptions::default(),
+        }
+    }
+}
+
+/// What `extract` did.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExtractReport {
+    /// Names of the entries that were written to disk.
+    pub extracted: Vec<String>,
+
+    /// Names of the entries that were skipped because they were already extracted.
+    pub skipped: Vec<String>,
+
+    /// Names of the entries that were skipped because of `OnConflict::Skip`.
+    pub skipped_existing: Vec<String>,
+
+    /// Paths existing files were moved to because of `OnConflict::Backup`.
+    pub backups: Vec<PathBuf>,
+
+    /// Entries extracted to a different path because of `CaseCollision::Rename`.
+    pub renamed: Vec<(String, PathBuf)>,
+
+    /// Names of the directory markers (see `EntryInfo::is_directory_marker`)
+    /// whose directory was created. These are not in `extracted`.
+    pub directories: Vec<String>,
+}
+
+/// Extract every entry in `table` into `output_dir`.
+///
+/// Directory markers create their (possibly empty) directory instead of a
+/// file, except with `ExtractOptions::flatten` where they are ignored.
+pub fn extract_all<P>(archive: &Archive, table: &EntryInfoTable, output_dir: P, options: &ExtractOptions) -> Result<ExtractReport>
+    where P: AsRef<Path> {
+    let entries = table.values().collect::<Vec<_>>();
+    extract_entries(archive, table, entries, output_dir.as_ref(), options)
+}
+
+/// Extract the named entries into `output_dir`.
+///
+/// # Errors
+///
+/// * If any of `names` is not in `table` this will return `Err(Error::NoSuchEntry)`
+///   before anything is written
+pub fn extract<P, S>(archive: &Archive, table: &EntryInfoTable, names: &[S], output_dir: P, options: &ExtractOptions) -> Result<ExtractReport>
+    where P: AsRef<Path>,
+          S: AsRef<str> {
+    let mut entries = Vec::with_capacity(names.len());
+    for name in names {
+        let name = name.as_ref();
+        let entry = table.get(name)
+            .ok_or_else(|| Error::NoSuchEntry { name: name.to_string() })?;
+        entries.push(entry);
+    }
+
+    extract_entries(archive, table, entries, output_dir.as_ref(), options)
+}
+
+fn extract_entries(archive: &Archive, table: &EntryInfoTable, mut entries: Vec<&EntryInfo>, output_dir: &Path, options: &ExtractOptions) -> Result<ExtractReport> {
+    let manifest = if options.restore_metadata {
+        archive.read_secret_data(table)?.and_then(Manifest::from_secret_data)
+    } else {
+        None
+    };
+
+    // Extracting in table order keeps reads of the archive sequential.
+    entries.sort_by_key(|e| e.index);
+    options.io.advise_map(archive.as_slice());
+
+    let mut report = ExtractReport::default();
+
+    let output_dir = match options.prefix {
+        Some(ref prefix) => output_dir.join(prefix),
+        None => output_dir.to_path_buf(),
+    };
+
+    // Files are written via the long form of `output_dir` but reported
+    // relative to the directory as it was given.
+    let given_output_dir = output_dir;
+    let output_dir = long_path(&given_output_dir)
+        .map_err(|e| Error::io(e, "resolving", &given_output_dir))?;
+    let reported = |path: &Path| given_output_dir.join(path.strip_prefix(&output_dir).unwrap_or(path));
+
+    let mut flattened = HashSet::new();
+
+    // Lowercased output path to the name of the entry extracted there.
+    let mut by_lowercase_path: HashMap<String, &str> = HashMap::new();
+
+    // Decide what to do with every entry up front so that `OnConflict::Fail`
+    // can refuse before anything has been written.
+    let mut planned = Vec::with_capacity(entries.len());
+    let mut directories = vec![];
+    for entry in entries {
+        if entry.is_directory_marker() {
+            if entry.len > 0 {
+                return Err(Error::DirectoryMarkerWithData { name: entry.name.clone(), len: entry.len });
+            }
+
+            if !options.flatten {
+                directories.push((entry, output_path(&output_dir, &entry.name)?));
+            }
+
+            continue;
+        }
+
+        let data = archive.get_bytes_via_entry(entry)?;
+        let transform = options.transforms.iter().find(|t| t.matches(&entry.name));
+
+        let mut output_file = if options.flatten {
+            output_path(&output_dir, &flatten_name(&entry.name, &mut flattened))?
+        } else {
+            output_path(&output_dir, &entry.name)?
+        };
+
+        if let Some(extension) = transform.and_then(|t| t.extension.as_ref()) {
+            output_file.set_extension(extension);
+        }
+
+        if options.on_case_collision != CaseCollision::Allow {
+            let lowercase = |path: &Path| path.to_string_lossy().to_lowercase();
+
+            if let Some(first) = by_lowercase_path.get(&lowercase(&output_file)) {
+                if options.on_case_collision == CaseCollision::Fail {
+                    return Err(Error::CaseCollision { first: first.to_string(), second: entry.name.clone() });
+                }
+
+                output_file = numbered_path(&output_file, |candidate| !by_lowercase_path.contains_key(&lowercase(candidate)));
+                report.renamed.push((entry.name.clone(), reported(&output_file)));
+            }
+
+            by_lowercase_path.insert(lowercase(&output_file), &entry.name);
+        }
+
+        // Transforming can be expensive and the result may be large, so only
+        // do it up front if the result is needed to decide what to do.
+        let mut transformed = None;
+        if options.resume != Resume::Disabled && output_file.is_file() {
+            transformed = Some(apply_transform(transform, entry, data)?);
+        }
+
+        let compare_to = transformed.as_ref().map(|d| d.as_ref()).unwrap_or(data);
+        if is_already_extracted(&output_file, compare_to, options.resume)? {
+            report.skipped.push(entry.name.clone());
+            continue;
+        }
+
+        let exists = fs::symlink_metadata(&output_file).is_ok();
+        if exists && options.on_conflict == OnConflict::Fail {
+            return Err(Error::DestinationExists { path: output_file.display().to_string() });
+        }
+
+        if exists && options.on_conflict == OnConflict::Skip {
+            report.skipped_existing.push(entry.name.clone());
+            continue;
+        }
+
+        planned.push((entry, data, transform, transformed, output_file, exists));
+    }
+
+    for (entry, directory) in directories {
+        fs::create_dir_all(&directory)
+            .map_err(|e| Error::io(e, "creating directory", &directory))?;
+        report.directories.push(entry.name.clone());
+    }
+
+    for (entry, data, transform, transformed, output_file, exists) in planned {
+        let data = match transformed {
+            Some(data) => data,
+            None => apply_transform(transform, entry, data)?,
+        };
+
+        if exists && options.on_conflict == OnConflict::Backup {
+            let backup = backup_path(&output_file);
+            fs::rename(&output_file, &backup)
+                .map_err(|e| Error::io(e, "backing up", &output_file))?;
+            report.backups.push(reported(&backup));
+        }
+
+        if let Some(parent) = output_file.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| Error::io(e, "creating directory", parent))?;
+        }
+
+        let mut f = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(&output_file)
+            .map_err(|e| Error::io(e, "creating", &output_file))?;
+
+        options.io.preallocate(&f, data.len() as u64)
+            .map_err(|e| Error::io(e, "allocating space for", &output_file))?;
+
+        f.write_all(&data)
+            .map_err(|e| Error::io(e, "writing", &output_file))?;
+        drop(f);
+
+        if let Some(md) = manifest.as_ref().and_then(|m| m.get(&entry.name)) {
+            md.apply(&output_file)?;
+        }
+
+        report.extracted.push(entry.name.clone());
+    }
+
+    Ok(report)
+}
+
+fn apply_transform<'a>(transform: Option<&Transform>, entry: &EntryInfo, data: &'a [u8]) -> Result<Cow<'a, [u8]>> {
+    match transform {
+        Some(transform) => (transform.apply)(&entry.name, data)
+            .map(Cow::Owned)
+            .map_err(|inner| Error::TransformFailed { inner, name: entry.name.clone() }),
+        None => Ok(Cow::Borrowed(data)),
+    }
+}
+
+/// The first of `path` with its file name changed to `stem (1).ext`,
+/// `stem (2).ext`, ... for which `is_available` returns `true`.
+fn numbered_path<F: Fn(&Path) -> bool>(path: &Path, is_available: F) -> PathBuf {
+    let stem = path.file_stem().unwrap_or_default();
+    let ext = path.extension();
+
+    let mut n = 1;
+    loop {
+        let mut file_name = stem.to_os_string();
+        file_name.push(format!(" ({})", n));
+        if let Some(ext) = ext {
+            file_name.push(".");
+            file_name.push(ext);
+        }
+
+        let candidate = path.with_file_name(file_name);
+        if is_available(&candidate) {
+            return candidate;
+        }
+
+        n += 1;
+    }
+}
+
+/// `name` split before its extension (including the `.`), if it has one.
+fn split_extension(name: &str) -> (&str, &str) {
+    match name.rfind('.') {
+        Some(0) | None => (name, ""),
+        Some(i) => name.split_at(i),
+    }
+}
+
+/// The last component of `name`, renamed to `stem (N).ext` if an earlier call
+/// already returned the same name.
+///
+/// Entries are flattened in table order so the renaming is stable for a given archive.
+pub fn flatten_name(name: &str, used: &mut HashSet<String>) -> String {
+    let file_name = name.rsplit(&['\\', '/'][..]).next().unwrap_or(name);
+
+    let (stem, ext) = split_extension(file_name);
+
+    let mut candidate = file_name.to_string();
+    let mut n = 1;
+    while used.contains(&candidate) {
+        candidate = format!("{} ({}){}", stem, n, ext);
+        n += 1;
+    }
+
+    used.insert(candidate.clone());
+    candidate
+}
+
+/// The first of `path.bak`, `path.bak.1`, `path.bak.2`, ... that does not exist.
+pub fn backup_path(path: &Path) -> PathBuf {
+    let mut name = path.file_name().map(|n| n.to_os_string()).unwrap_or_default();
+    name.push(".bak");
+
+    let mut candidate = path.with_file_name(&name);
+    let mut n = 1;
+    while fs::symlink_metadata(&candidate).is_ok() {
+        let mut numbered = name.clone();
+        numbered.push(format!(".{}", n));
+        candidate = path.with_file_name(numbered);
+        n += 1;
+    }
+
+    candidate
+}
+
+/// The path an entry named `name` is extracted to inside of `output_dir`.
+///
+/// Both `\` and `/` are treated as separators. The path is built one
+/// component at a time so it only contains the platform's separator, which
+/// `\\?\` paths (see `long_path`) require on Windows.
+///
+/// # Errors
+///
+/// * If `name` is absolute or contains `..` (and so would be written outside
+///   of `output_dir`) this will return `Err(Error::UnsafeEntryName)`
+pub fn output_path(output_dir: &Path, name: &str) -> Result<PathBuf> {
+    let unsafe_name = || Error::UnsafeEntryName { name: name.to_string() };
+
+    if name.starts_with(&['\\', '/'][..]) {
+        return Err(unsafe_name());
+    }
+
+    let mut path = output_dir.to_path_buf();
+    for part in name.split(&['\\', '/'][..]) {
+        // On Windows a part may still be a drive such as `C:`.
+        let mut components = Path::new(part).components();
+        match (components.next(), components.next()) {
+            (None, _) | (Some(Component::CurDir), None) => {},
+            (Some(Component::Normal(part)), None) => path.push(part),
+            _ => return Err(unsafe_name()),
+        }
+    }
+
+    Ok(path)
+}
+
+/// `path` in the form that is not limited to 260 characters (`MAX_PATH`) on
+/// Windows: absolute and prefixed with `\\?\` (`\\?\UNC\` for network shares).
+///
+/// Other platforms have no such limit so `path` is returned as is.
+#[cfg(windows)]
+pub fn long_path(path: &Path) -> io::Result<PathBuf> {
+    use std::ffi::OsString;
+    use std::path::{self, Prefix};
+
+    // Windows does not normalize `\\?\` paths so `.`, `..`, and `/` have to
+    // be resolved first.
+    let absolute = path::absolute(path)?;
+    let mut components = absolute.components();
+
+    let prefix = match components.next() {
+        Some(Component::Prefix(prefix)) => prefix,
+        _ => return Ok(absolute),
+    };
+
+    let mut long = OsString::new();
+    match prefix.kind() {
+        Prefix::Disk(_) => {
+            long.push(r"\\?\");
+            long.push(prefix.as_os_str());
+        },
+        Prefix::UNC(server, share) => {
+            long.push(r"\\?\UNC\");
+            long.push(server);
+            long.push(r"\");
+            long.push(share);
+        },
+        // Already verbatim, or a device such as `\\.\COM1`.
+        _ => return Ok(absolute),
+    }
+
+    long.push(r"\");
+    let mut long = PathBuf::from(long);
+    for component in components {
+        if let Component::Normal(part) = component {
+            long.push(part);
+        }
+    }
+
+    Ok(long)
+}
+
+/// `path` in the form that is not limited to 260 characters (`MAX_PATH`) on
+/// Windows.
+///
+/// Other platforms have no such limit so `path` is returned as is.
+#[cfg(not(windows))]
+pub fn long_path(path: &Path) -> io::Result<PathBuf> {
+    Ok(path.to_path_buf())
+}
+
+fn is_already_extracted(path: &Path, data: &[u8], resume: Resume) -> Result<bool> {
+    if resume == Resume::Disabled {
+        return Ok(false);
+    }
+
+    let md = match fs::metadata(path) {
+        Ok(md) => md,
+        Err(_) => return Ok(false),
+    };
+
+    if !md.is_file() || md.len() != data.len() as u64 {
+        return Ok(false);
+    }
+
+    if resume == Resume::MatchingSize {
+        return Ok(true);
+    }
+
+    let mut existing = Vec::with_capacity(data.len());
+    File::open(path)
+        .and_then(|mut f| f.read_to_end(&mut existing))
+        .map_err(|e| Error::io(e, "reading", path))?;
+
+    Ok(crc32::checksum(&existing) == crc32::checksum(data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::{packer, Kind};
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!("easage-extract-{}-{}", name, ::std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn archive() -> Archive {
+        packer::pack(vec![
+            ("Data\\INI\\a.ini", &b"aaaa"[..]),
+            ("Data\\INI\\b.ini", &b"bbbb"[..]),
+        ], Kind::BigF).unwrap()
+    }
+
+    #[test]
+    fn extract_all_writes_entries() {
+        let dir = temp_dir("all");
+        let archive = archive();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let report = extract_all(&archive, &table, &dir, &ExtractOptions::default()).unwrap();
+
+        assert_eq!(report.extracted, vec!["Data\\INI\\a.ini".to_string(), "Data\\INI\\b.ini".to_string()]);
+        assert_eq!(fs::read(dir.join("Data/INI/a.ini")).unwrap(), b"aaaa");
+        assert_eq!(fs::read(dir.join("Data/INI/b.ini")).unwrap(), b"bbbb");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_all_final_big() {
+        let dir = temp_dir("final-big");
+        let archive = Archive::from_bytes(&::testing::final_big(&[("a", b"aaaa"), ("b", b"bb")])).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let report = extract_all(&archive, &table, &dir, &ExtractOptions::default()).unwrap();
+        assert_eq!(report.extracted, vec!["a".to_string(), "b".to_string()]);
+        assert_eq!(fs::read(dir.join("b")).unwrap(), b"bb");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_named_entries() {
+        let dir = temp_dir("named");
+        let archive = archive();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let report = extract(&archive, &table, &["Data\\INI\\b.ini"], &dir, &ExtractOptions::default()).unwrap();
+        assert_eq!(report.extracted, vec!["Data\\INI\\b.ini".to_string()]);
+        assert!(!dir.join("Data/INI/a.ini").exists());
+
+        let res = extract(&archive, &table, &["nope"], &dir, &ExtractOptions::default());
+        assert_matches!(res, Err(Error::NoSuchEntry { .. }));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_resume() {
+        let dir = temp_dir("resume");
+        let archive = archive();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        fs::create_dir_all(dir.join("Data/INI")).unwrap();
+        fs::write(dir.join("Data/INI/a.ini"), b"aaaa").unwrap();
+        fs::write(dir.join("Data/INI/b.ini"), b"XXXX").unwrap();
+
+        let options = ExtractOptions { resume: Resume::MatchingSize, on_conflict: OnConflict::Overwrite, ..ExtractOptions::default() };
+        let report = extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(report.skipped.len(), 2);
+
+        let options = ExtractOptions { resume: Resume::MatchingChecksum, on_conflict: OnConflict::Overwrite, ..ExtractOptions::default() };
+        let report = extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(report.skipped, vec!["Data\\INI\\a.ini".to_string()]);
+        assert_eq!(report.extracted, vec!["Data\\INI\\b.ini".to_string()]);
+        assert_eq!(fs::read(dir.join("Data/INI/b.ini")).unwrap(), b"bbbb");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_concurrently() {
+        let dir = temp_dir("concurrent");
+        let archive = archive();
+        let table = archive.table().unwrap();
+
+        // Every thread shares the same archive and table by reference.
+        ::std::thread::scope(|scope| {
+            for name in table.keys() {
+                let dir = &dir;
+                let archive = &archive;
+                scope.spawn(move || extract(archive, table, &[name], dir, &ExtractOptions::default()).unwrap());
+            }
+        });
+
+        assert_eq!(fs::read(dir.join("Data/INI/a.ini")).unwrap(), b"aaaa");
+        assert_eq!(fs::read(dir.join("Data/INI/b.ini")).unwrap(), b"bbbb");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_on_conflict() {
+        let dir = temp_dir("conflict");
+        let archive = archive();
+        let table = archive.read_entry_metadata_table().unwrap();
+        let a = dir.join("Data/INI/a.ini");
+
+        fs::create_dir_all(dir.join("Data/INI")).unwrap();
+        fs::write(&a, b"user edit").unwrap();
+
+        let options = ExtractOptions { on_conflict: OnConflict::Fail, ..ExtractOptions::default() };
+        assert_matches!(extract_all(&archive, &table, &dir, &options), Err(Error::DestinationExists { .. }));
+        assert!(!dir.join("Data/INI/b.ini").exists());
+
+        let options = ExtractOptions { on_conflict: OnConflict::Skip, ..ExtractOptions::default() };
+        let report = extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(report.skipped_existing, vec!["Data\\INI\\a.ini".to_string()]);
+        assert_eq!(fs::read(&a).unwrap(), b"user edit");
+
+        let options = ExtractOptions { on_conflict: OnConflict::Backup, ..ExtractOptions::default() };
+        let report = extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(report.backups, vec![dir.join("Data/INI/a.ini.bak"), dir.join("Data/INI/b.ini.bak")]);
+        assert_eq!(fs::read(dir.join("Data/INI/a.ini.bak")).unwrap(), b"user edit");
+        assert_eq!(fs::read(&a).unwrap(), b"aaaa");
+
+        let report = extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(report.backups[0], dir.join("Data/INI/a.ini.bak.1"));
+
+        let options = ExtractOptions { on_conflict: OnConflict::Overwrite, ..ExtractOptions::default() };
+        let report = extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(report.extracted.len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_flatten_with_prefix() {
+        let dir = temp_dir("flatten");
+        let archive = packer::pack(vec![
+            ("Data\\INI\\a.ini", &b"1"[..]),
+            ("Data\\INI\\Default\\a.ini", &b"2"[..]),
+            ("Art\\a.ini", &b"3"[..]),
+        ], Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let options = ExtractOptions { flatten: true, prefix: Some(PathBuf::from("mod")), ..ExtractOptions::default() };
+        extract_all(&archive, &table, &dir, &options).unwrap();
+
+        let mut names = fs::read_dir(dir.join("mod")).unwrap()
+            .map(|e| e.unwrap().file_name().into_string().unwrap())
+            .collect::<Vec<_>>();
+        names.sort();
+        assert_eq!(names, vec!["a (1).ini", "a (2).ini", "a.ini"]);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn flatten_name_renames_collisions() {
+        let mut used = HashSet::new();
+
+        assert_eq!(flatten_name("Data\\a.ini", &mut used), "a.ini");
+        assert_eq!(flatten_name("Art/a.ini", &mut used), "a (1).ini");
+        assert_eq!(flatten_name(".gitignore", &mut used), ".gitignore");
+        assert_eq!(flatten_name("x\\.gitignore", &mut used), ".gitignore (1)");
+        assert_eq!(flatten_name("README", &mut used), "README");
+    }
+
+    #[test]
+    fn output_path_rejects_escapes() {
+        let dir = Path::new("out");
+
+        assert_eq!(output_path(dir, "Data\\a.ini").unwrap(), dir.join("Data").join("a.ini"));
+        assert_matches!(output_path(dir, "..\\a.ini"), Err(Error::UnsafeEntryName { .. }));
+        assert_matches!(output_path(dir, "/etc/passwd"), Err(Error::UnsafeEntryName { .. }));
+        assert_matches!(output_path(dir, "\\Windows\\a.ini"), Err(Error::UnsafeEntryName { .. }));
+        assert_matches!(output_path(dir, "Data\\..\\..\\a.ini"), Err(Error::UnsafeEntryName { .. }));
+
+        // Mixed and repeated separators never end up in the path.
+        assert_eq!(output_path(dir, "Data/.\\INI//a.ini").unwrap(), dir.join("Data").join("INI").join("a.ini"));
+    }
+
+    #[test]
+    fn extract_transform() {
+        let dir = temp_dir("transform");
+        let archive = packer::pack(vec![
+            ("Data\\a.ini", &b"aaaa"[..]),
+            ("Art\\b.tga", &b"tga"[..]),
+            ("Art\\c.dds", &b"dds"[..]),
+        ], Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let options = ExtractOptions {
+            transforms: vec![
+                Transform::new(|name| name.ends_with(".ini"), |_, data| Ok(data.to_ascii_uppercase())),
+                Transform::new(|name| name.ends_with(".tga"), |name, data| Ok([name.as_bytes(), data].concat()))
+                    .with_extension("png"),
+                Transform::new(|name| name.ends_with(".ini"), |_, _| Ok(b"unused".to_vec())),
+            ],
+            ..ExtractOptions::default()
+        };
+
+        extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(fs::read(dir.join("Data/a.ini")).unwrap(), b"AAAA");
+        assert_eq!(fs::read(dir.join("Art/b.png")).unwrap(), b"Art\\b.tgatga");
+        assert!(!dir.join("Art/b.tga").exists());
+        assert_eq!(fs::read(dir.join("Art/c.dds")).unwrap(), b"dds");
+
+        // Resuming compares existing files against the transformed data.
+        let options = ExtractOptions { resume: Resume::MatchingChecksum, ..options };
+        let report = extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(report.skipped.len(), 3);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_transform_failure() {
+        let dir = temp_dir("transform-failure");
+        let archive = archive();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let options = ExtractOptions {
+            transforms: vec![Transform::new(|_| true, |_, _| Err(io::Error::new(io::ErrorKind::InvalidData, "bad image")))],
+            ..ExtractOptions::default()
+        };
+
+        assert_matches!(extract_all(&archive, &table, &dir, &options),
+            Err(Error::TransformFailed { ref name, .. }) if name == "Data\\INI\\a.ini");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_case_collision() {
+        let dir = temp_dir("case-collision");
+        let archive = packer::pack(vec![
+            ("Data\\a.ini", &b"first"[..]),
+            ("DATA\\A.INI", &b"second"[..]),
+            ("b.ini", &b"b"[..]),
+        ], Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let options = ExtractOptions { on_case_collision: CaseCollision::Fail, ..ExtractOptions::default() };
+        assert_matches!(extract_all(&archive, &table, &dir, &options),
+            Err(Error::CaseCollision { ref first, ref second }) if first == "Data\\a.ini" && second == "DATA\\A.INI");
+        assert!(fs::read_dir(&dir).unwrap().next().is_none());
+
+        let options = ExtractOptions { on_case_collision: CaseCollision::Rename, ..ExtractOptions::default() };
+        let report = extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(report.renamed, vec![("DATA\\A.INI".to_string(), dir.join("DATA/A (1).INI"))]);
+        assert_eq!(fs::read(dir.join("Data/a.ini")).unwrap(), b"first");
+        assert_eq!(fs::read(dir.join("DATA/A (1).INI")).unwrap(), b"second");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn extract_long_paths() {
+        let dir = temp_dir("long-paths");
+
+        // Well over the 260 characters Windows allows without `\\?\`.
+        let name = (0..8)
+            .map(|i| format!("{}{:02}", "d".repeat(48), i))
+            .collect::<Vec<_>>()
+            .join("\\") + "\\a.ini";
+        assert!(dir.join(&name).as_os_str().len() > 400);
+
+        let archive = packer::pack(vec![(name.as_str(), &b"deep"[..])], Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let report = extract_all(&archive, &table, &dir, &ExtractOptions::default()).unwrap();
+        assert_eq!(report.extracted, vec![name.clone()]);
+
+        let path = output_path(&long_path(&dir).unwrap(), &name).unwrap();
+        assert_eq!(fs::read(&path).unwrap(), b"deep");
+
+        fs::remove_dir_all(long_path(&dir).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn extract_unicode_paths() {
+        let dir = temp_dir("ünïcødé-输出");
+        let archive = packer::pack(vec![
+            ("Données\\Ünïcödé\\日本語.ini", &b"first"[..]),
+            ("DONNÉES\\ÜNÏCÖDÉ\\日本語.INI", &b"second"[..]),
+            ("Карты\\карта.map", &b"map"[..]),
+        ], Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let options = ExtractOptions { on_case_collision: CaseCollision::Rename, ..ExtractOptions::default() };
+        let report = extract_all(&archive, &table, &dir, &options).unwrap();
+        assert_eq!(report.renamed, vec![("DONNÉES\\ÜNÏCÖDÉ\\日本語.INI".to_string(), dir.join("DONNÉES/ÜNÏCÖDÉ/日本語 (1).INI"))]);
+
+        assert_eq!(fs::read(dir.join("Données/Ünïcödé/日本語.ini")).unwrap(), b"first");
+        assert_eq!(fs::read(dir.join("DONNÉES/ÜNÏCÖDÉ/日本語 (1).INI")).unwrap(), b"second");
+        assert_eq!(fs::read(dir.join("Карты/карта.map")).unwrap(), b"map");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn extract_into_non_utf8_directory() {
+        use std::ffi::OsStr;
+        use std::os::unix::ffi::OsStrExt;
+
+        let dir = temp_dir("non-utf8").join(OsStr::from_bytes(b"out-\xFF"));
+        let archive = packer::pack(vec![
+            ("Data\\a.ini", &b"first"[..]),
+            ("DATA\\A.INI", &b"second"[..]),
+        ], Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let options = ExtractOptions { on_case_collision: CaseCollision::Rename, ..ExtractOptions::default() };
+        extract_all(&archive, &table, &dir, &options).unwrap();
+
+        assert_eq!(fs::read(dir.join("Data/a.ini")).unwrap(), b"first");
+        assert_eq!(fs::read(dir.join("DATA/A (1).INI")).unwrap(), b"second");
+
+        fs::remove_dir_all(dir.parent().unwrap()).unwrap();
+    }
+
+    #[cfg(windows)]
+    #[test]
+    fn long_path_prefixes() {
+        assert_eq!(long_path(Path::new(r"C:\Games\..\Mods/out")).unwrap(), Path::new(r"\\?\C:\Mods\out"));
+        assert_eq!(long_path(Path::new(r"\\server\share\out")).unwrap(), Path::new(r"\\?\UNC\server\share\out"));
+        assert_eq!(long_path(Path::new(r"\\?\C:\out")).unwrap(), Path::new(r"\\?\C:\out"));
+        assert!(long_path(Path::new("out")).unwrap().starts_with(r"\\?\"));
+    }
+
+    #[test]
+    fn extract_directory_markers_and_empty_entries() {
+        let dir = temp_dir("markers");
+        let archive = packer::pack(vec![
+            ("Data\\Empty\\", &b""[..]),
+            ("Data\\empty.ini", &b""[..]),
+            ("Maps/", &b""[..]),
+        ], Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        let report = extract_all(&archive, &table, &dir, &ExtractOptions::default()).unwrap();
+        assert_eq!(report.directories, vec!["Data\\Empty\\".to_string(), "Maps/".to_string()]);
+        assert_eq!(report.extracted, vec!["Data\\empty.ini".to_string()]);
+        assert!(dir.join("Data/Empty").is_dir());
+        assert!(dir.join("Maps").is_dir());
+        assert_eq!(fs::read(dir.join("Data/empty.ini")).unwrap(), b"");
+
+        let archive = packer::pack(vec![("Data\\", &b"data"[..])], Kind::BigF).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        let options = ExtractOptions { on_conflict: OnConflict::Overwrite, ..ExtractOptions::default() };
+        assert_matches!(extract_all(&archive, &table, &dir, &options),
+            Err(Error::DirectoryMarkerWithData { len: 4, .. }));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
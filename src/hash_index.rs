@@ -0,0 +1,207 @@
+//! Look entries up by a hash of their name.
+//!
+//! Some SAGE-derived engines refer to assets by a CRC of the entry name
+//! instead of the name itself. A `HashIndex` maps such hashes back to the
+//! entries of an archive (see `Archive::build_hash_index`).
+//!
+//! Engines differ in both the CRC and how they normalize names before hashing
+//! them, so `HashFn` combines the two. The common variants are built in and
+//! any other hash can be plugged in with `HashFn::new`.
+
+use std::collections::HashMap;
+
+use ::crc32;
+use ::{EntryInfo, EntryInfoTable};
+
+/// How entry names are hashed: a hash function over the bytes of the
+/// (optionally normalized) UTF-8 name.
+#[derive(Debug, Copy, Clone)]
+pub struct HashFn {
+    hash: fn(&[u8]) -> u32,
+    lowercase: bool,
+    backslashes: bool,
+}
+
+impl HashFn {
+    /// CRC-32 (as used by zip and PNG).
+    pub const CRC32: HashFn = HashFn::new(crc32::checksum);
+
+    /// CRC-32 without the final inversion (CRC-32/JAMCRC).
+    pub const CRC32_JAMCRC: HashFn = HashFn::new(crc32::jamcrc);
+
+    /// CRC-32C, with the Castagnoli polynomial.
+    pub const CRC32C: HashFn = HashFn::new(crc32::castagnoli);
+
+    /// CRC-32 computed most significant bit first (CRC-32/BZIP2).
+    pub const CRC32_BZIP2: HashFn = HashFn::new(crc32::bzip2);
+
+    /// CRC-32/BZIP2 without the final inversion (CRC-32/MPEG-2).
+    pub const CRC32_MPEG2: HashFn = HashFn::new(crc32::mpeg2);
+
+    /// Hash names with `hash` exactly as they are stored in the archive.
+    pub const fn new(hash: fn(&[u8]) -> u32) -> Self {
+        HashFn {
+            hash,
+            lowercase: false,
+            backslashes: false,
+        }
+    }
+
+    /// Lowercase ASCII letters in names before hashing them, for engines
+    /// that look entries up case-insensitively.
+    pub const fn lowercase(mut self) -> Self {
+        self.lowercase = true;
+        self
+    }
+
+    /// Replace `/` with `\` in names before hashing them, for engines that
+    /// treat both as path separators.
+    pub const fn backslashes(mut self) -> Self {
+        self.backslashes = true;
+        self
+    }
+
+    /// The hash of the entry named `name`.
+    pub fn hash(&self, name: &str) -> u32 {
+        if !self.lowercase && !self.backslashes {
+            return (self.hash)(name.as_bytes());
+        }
+
+        let normalized = name.bytes()
+            .map(|b| if self.lowercase { b.to_ascii_lowercase() } else { b })
+            .map(|b| if self.backslashes && b == b'/' { b'\\' } else { b })
+            .collect::<Vec<_>>();
+
+        (self.hash)(&normalized)
+    }
+}
+
+/// The entries of a table by the hash of their name.
+#[derive(Debug, Clone)]
+pub struct HashIndex<'a> {
+    hash: HashFn,
+
+    /// Entries in table order.
+    entries: HashMap<u32, Vec<&'a EntryInfo>>,
+}
+
+impl<'a> HashIndex<'a> {
+    /// Index every entry of `table` by `hash` of its name.
+    pub fn new(table: &'a EntryInfoTable, hash: HashFn) -> Self {
+        let mut entries: HashMap<u32, Vec<&'a EntryInfo>> = HashMap::with_capacity(table.len());
+        for entry in table.values() {
+            entries.entry(hash.hash(&entry.name))
+                .or_default()
+                .push(entry);
+        }
+
+        HashIndex { hash, entries }
+    }
+
+    /// The function names were hashed with.
+    pub fn hash_fn(&self) -> HashFn {
+        self.hash
+    }
+
+    /// The entry whose name hashes to `hash`.
+    ///
+    /// If the names of several entries hash to `hash` the one that appears
+    /// first in the table is returned (see `collisions`).
+    pub fn get(&self, hash: u32) -> Option<&'a EntryInfo> {
+        self.entries.get(&hash).map(|entries| entries[0])
+    }
+
+    /// Every entry whose name hashes to `hash`, in table order.
+    pub fn get_all(&self, hash: u32) -> &[&'a EntryInfo] {
+        self.entries.get(&hash).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// The entry that `name` refers to after normalizing it like `HashFn`
+    /// does (for example a differently cased name with `HashFn::lowercase`).
+    pub fn get_by_name(&self, name: &str) -> Option<&'a EntryInfo> {
+        self.get(self.hash.hash(name))
+    }
+
+    /// The hashes shared by the names of more than one entry, and those entries.
+    ///
+    /// An engine that looks entries up by such a hash can only ever load one of them.
+    pub fn collisions(&self) -> Vec<(u32, &[&'a EntryInfo])> {
+        let mut collisions = self.entries.iter()
+            .filter(|&(_, entries)| entries.len() > 1)
+            .map(|(&hash, entries)| (hash, entries.as_slice()))
+            .collect::<Vec<_>>();
+
+        collisions.sort_by_key(|&(_, entries)| entries[0].index);
+        collisions
+    }
+
+    /// The number of distinct hashes.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::{packer, Kind};
+
+    #[test]
+    fn hash_fn_normalizes_names() {
+        assert_eq!(HashFn::CRC32.hash("123456789"), 0xCBF4_3926);
+        assert_eq!(HashFn::CRC32C.hash("123456789"), 0xE306_9283);
+
+        let engine = HashFn::CRC32.lowercase().backslashes();
+        assert_eq!(engine.hash("Data/INI/Weapon.ini"), HashFn::CRC32.hash("data\\ini\\weapon.ini"));
+        assert_ne!(HashFn::CRC32.hash("Data/INI/Weapon.ini"), HashFn::CRC32.hash("data\\ini\\weapon.ini"));
+    }
+
+    #[test]
+    fn build_hash_index() {
+        let archive = packer::pack(vec![
+            ("Data\\INI\\Weapon.ini", b"weapon"),
+            ("Art\\Textures\\a.dds", b"texture"),
+        ], Kind::BigF).unwrap();
+
+        let hash = HashFn::CRC32.lowercase();
+        let index = archive.build_hash_index(hash).unwrap();
+        assert_eq!(index.len(), 2);
+
+        let entry = index.get(HashFn::CRC32.hash("data\\ini\\weapon.ini")).unwrap();
+        assert_eq!(entry.name, "Data\\INI\\Weapon.ini");
+        assert_eq!(archive.get_bytes_via_entry(entry).unwrap(), b"weapon");
+
+        assert_eq!(index.get_by_name("ART\\TEXTURES\\A.DDS").unwrap().name, "Art\\Textures\\a.dds");
+        assert_matches!(index.get(HashFn::CRC32.hash("missing.ini")), None);
+        assert!(index.collisions().is_empty());
+    }
+
+    #[test]
+    fn hash_index_collisions() {
+        let archive = packer::pack(vec![
+            ("a.ini", b"a"),
+            ("b.ini", b"b"),
+            ("c.ini", b"c"),
+        ], Kind::BigF).unwrap();
+
+        fn starts_with_c(name: &[u8]) -> u32 {
+            u32::from(name[0] == b'c')
+        }
+
+        let table = archive.read_entry_metadata_table().unwrap();
+        let index = HashIndex::new(&table, HashFn::new(starts_with_c));
+
+        let names = index.get_all(0).iter().map(|e| e.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["a.ini", "b.ini"]);
+        assert_eq!(index.get(0).unwrap().name, "a.ini");
+        assert_eq!(index.get(1).unwrap().name, "c.ini");
+
+        let collisions = index.collisions();
+        assert_eq!(collisions.len(), 1);
+        assert_eq!(collisions[0].0, 0);
+    }
+}
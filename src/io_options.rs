@@ -0,0 +1,176 @@
+//! Buffer sizes and operating system hints that tune how files are read and written.
+//!
+//! None of these change what is read or written, only how fast. Hints are
+//! ignored on platforms (or filesystems) that do not support them. Everything
+//! is disabled by default, which matches what easage did before these existed.
+//!
+//! Spinning disks and network shares benefit the most: `sequential` lets the
+//! operating system read ahead further and `preallocate` lets the filesystem
+//! reserve contiguous space up front instead of growing the file as it is written.
+
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct IoOptions {
+    /// The capacity of the buffer `buffered` wraps writers in.
+    pub buffer_size: usize,
+
+    /// Advise the operating system that archives and source files will be
+    /// read sequentially (`madvise`/`posix_fadvise` on Unix).
+    pub sequential: bool,
+
+    /// Reserve the final size of output files before writing them
+    /// (`fallocate` on Linux).
+    pub preallocate: bool,
+}
+
+impl IoOptions {
+    /// The buffer size used by `std::io::BufWriter`.
+    pub const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+    /// Wrap `inner` in a `BufWriter` with a `buffer_size` buffer.
+    pub fn buffered<W: Write>(&self, inner: W) -> BufWriter<W> {
+        BufWriter::with_capacity(self.buffer_size, inner)
+    }
+
+    /// With `sequential`, advise that the memory-mapped `data` (such as
+    /// `Archive::as_slice`) will be read from start to end.
+    pub fn advise_map(&self, data: &[u8]) {
+        if self.sequential {
+            sys::advise_map_sequential(data);
+        }
+    }
+
+    /// With `sequential`, advise that `file` will be read from start to end.
+    pub fn advise_file(&self, file: &File) {
+        if self.sequential {
+            sys::advise_file_sequential(file);
+        }
+    }
+
+    /// With `preallocate`, reserve `len` bytes for `file`.
+    ///
+    /// The file may become `len` bytes long (filled with zeroes) so `len`
+    /// must be the exact size that will be written. Filesystems that can not
+    /// preallocate are not an error.
+    ///
+    /// # Errors
+    ///
+    /// * If there is not enough space for `len` bytes this will return the `io::Error`
+    pub fn preallocate(&self, file: &File, len: u64) -> io::Result<()> {
+        if self.preallocate && len > 0 {
+            sys::preallocate(file, len)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for IoOptions {
+    fn default() -> Self {
+        IoOptions {
+            buffer_size: Self::DEFAULT_BUFFER_SIZE,
+            sequential: false,
+            preallocate: false,
+        }
+    }
+}
+
+#[cfg(unix)]
+mod sys {
+    use std::fs::File;
+    use std::io;
+    use std::os::unix::io::AsRawFd;
+
+    use ::libc;
+
+    pub fn advise_map_sequential(data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+
+        // `madvise` requires a page-aligned address.
+        let page_size = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as usize;
+        let addr = data.as_ptr() as usize;
+        let aligned = addr - addr % page_size;
+
+        // A hint: failure only means the hint is not applied.
+        unsafe {
+            libc::madvise(aligned as *mut libc::c_void, data.len() + (addr - aligned), libc::MADV_SEQUENTIAL);
+        }
+    }
+
+    #[cfg(any(target_os = "linux", target_os = "android", target_os = "freebsd"))]
+    pub fn advise_file_sequential(file: &File) {
+        unsafe {
+            libc::posix_fadvise(file.as_raw_fd(), 0, 0, libc::POSIX_FADV_SEQUENTIAL);
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android", target_os = "freebsd")))]
+    pub fn advise_file_sequential(_file: &File) {}
+
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    pub fn preallocate(file: &File, len: u64) -> io::Result<()> {
+        // Unlike `posix_fallocate` this fails instead of writing zeroes when
+        // the filesystem can not preallocate, which would be slower than not
+        // preallocating at all.
+        if unsafe { libc::fallocate(file.as_raw_fd(), 0, 0, len as libc::off_t) } == 0 {
+            return Ok(());
+        }
+
+        let e = io::Error::last_os_error();
+        match e.raw_os_error() {
+            Some(libc::EOPNOTSUPP) | Some(libc::ENOSYS) => Ok(()),
+            _ => Err(e),
+        }
+    }
+
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    pub fn preallocate(_file: &File, _len: u64) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(not(unix))]
+mod sys {
+    use std::fs::File;
+    use std::io;
+
+    pub fn advise_map_sequential(_data: &[u8]) {}
+
+    pub fn advise_file_sequential(_file: &File) {}
+
+    pub fn preallocate(_file: &File, _len: u64) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn preallocate_does_not_change_contents() {
+        use std::fs;
+
+        let path = ::std::env::temp_dir().join(format!("easage-io-options-{}", ::std::process::id()));
+        let io = IoOptions { sequential: true, preallocate: true, ..IoOptions::default() };
+
+        let mut file = File::create(&path).unwrap();
+        io.preallocate(&file, 4).unwrap();
+        io.advise_file(&file);
+        io.advise_map(b"not a map");
+
+        let mut writer = io.buffered(&mut file);
+        writer.write_all(b"data").unwrap();
+        writer.flush().unwrap();
+        drop(writer);
+        drop(file);
+
+        assert_eq!(fs::read(&path).unwrap(), b"data");
+        fs::remove_file(&path).unwrap();
+    }
+}
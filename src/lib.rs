@@ -17,11 +17,24 @@
 //!
 //! All errors implement the `Display` trait so feel free to print away!
 //!
+//! Where possible each variant carries the context needed to act on it
+//! (the entry name, the path on disk, or the offending byte range).
+//!
 //! <small>
 //! Side note: I don't think I am using all the goodies `failure` has to offer.
 //! Please let me know if you see something that can be improved.
 //! </small>
 //!
+//! # Serialization
+//!
+//! Enable the `serde` feature to derive `Serialize` and `Deserialize` for
+//! metadata types such as `Kind`, `EntryInfo`, `Header`, and validation reports.
+//!
+//! # Testing
+//!
+//! Enable the `test-util` feature for the `testing` module which builds
+//! truncated, overlapping, and otherwise broken archives in memory.
+//!
 //! # Examples
 //!
 //! Read an archive from a file:
@@ -46,7 +59,7 @@
 //! ```rust,no_run
 //! use easage::Archive;
 //!
-//! let mut archive = Archive::from_path("path/to/your.big").unwrap();
+//! let archive = Archive::from_path("path/to/your.big").unwrap();
 //!
 //! // This provides us with a lookup table so we don't
 //! // have to read the header repeatedly.
@@ -66,6 +79,19 @@
 //! }
 //! ```
 //!
+//! Extract every entry of an archive into a directory:
+//!
+//! ```rust,no_run
+//! use easage::Archive;
+//! use easage::extract::{self, ExtractOptions};
+//!
+//! let archive = Archive::from_path("path/to/your.big").unwrap();
+//! let table = archive.read_entry_metadata_table().unwrap();
+//!
+//! let report = extract::extract_all(&archive, &table, "output/directory", &ExtractOptions::default()).unwrap();
+//! println!("Extracted {} entries", report.extracted.len());
+//! ```
+//!
 //! Package a directory (recursively) into an archive:
 //!
 //! ```rust,no_run
@@ -85,6 +111,9 @@
 //!
 //!     // The "magic" identifier (this isn't important yet)
 //!     kind: Kind::BigF,
+//!
+//!     // Remaining settings (such as storing file metadata) keep their defaults.
+//!     ..Settings::default()
 //! };
 //!
 //! // Finally we can create our archive!
@@ -117,14 +146,53 @@ extern crate memmap;
 extern crate owning_ref;
 extern crate walkdir;
 
+#[cfg(unix)]
+extern crate libc;
+
 #[macro_use(Fail)]
 extern crate failure;
 
+#[cfg(feature = "serde")]
+#[macro_use]
+extern crate serde;
+
 mod archive;
-pub use archive::{Kind, EntryInfoTable, EntryInfo, Archive};
+pub use archive::{Kind, EntryInfo, Header, Archive, Format, HeaderField, Endianness};
+
+mod table;
+pub use table::EntryInfoTable;
+
+pub mod archive_set;
+pub use archive_set::ArchiveSet;
 
 pub mod packer;
 
+pub mod extract;
+
+mod crc32;
+
+pub mod hash_index;
+pub use hash_index::{HashFn, HashIndex};
+
+pub mod validation;
+
+pub mod lint;
+
+pub mod secret_data;
+
+pub mod metadata;
+
+pub mod checksums;
+
+#[cfg(any(test, feature = "test-util"))]
+pub mod testing;
+
+pub mod limits;
+pub use limits::Limits;
+
+pub mod io_options;
+pub use io_options::IoOptions;
+
 mod error;
 pub use error::{Result, Error};
 
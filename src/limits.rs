@@ -0,0 +1,103 @@
+//! Caps on the resources reading or packing an archive may consume.
+//!
+//! Services that accept archives from untrusted users should set these so a
+//! malicious archive can not exhaust memory. Every limit is disabled by default.
+//!
+//! NOTE: easage does not decompress entries yet so sizes are the sizes stored
+//! in the table. Decompressors must check the *decompressed* sizes with
+//! `check_entry_size` and `check_total_size` as they go.
+
+use ::{Error, Result};
+
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Limits {
+    /// The maximum number of entries in a table.
+    pub max_entries: Option<usize>,
+
+    /// The maximum size of a single entry's data in bytes.
+    pub max_entry_size: Option<u64>,
+
+    /// The maximum combined size of all entries' data in bytes.
+    pub max_total_size: Option<u64>,
+}
+
+impl Limits {
+    /// No limits at all (the default).
+    pub fn unlimited() -> Self {
+        Self::default()
+    }
+
+    /// # Errors
+    ///
+    /// * If `count` exceeds `max_entries` this will return `Err(Error::TooManyEntries)`
+    pub fn check_entry_count(&self, count: usize) -> Result<()> {
+        match self.max_entries {
+            Some(max) if count > max => Err(Error::TooManyEntries { count, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// * If `size` exceeds `max_entry_size` this will return `Err(Error::EntryTooLarge)`
+    pub fn check_entry_size(&self, name: &str, size: u64) -> Result<()> {
+        match self.max_entry_size {
+            Some(max) if size > max => Err(Error::EntryTooLarge { name: name.to_string(), size, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// # Errors
+    ///
+    /// * If `size` exceeds `max_total_size` this will return `Err(Error::TotalSizeTooLarge)`
+    pub fn check_total_size(&self, size: u64) -> Result<()> {
+        match self.max_total_size {
+            Some(max) if size > max => Err(Error::TotalSizeTooLarge { size, max }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Check the number of entries, each entry's size, and the total size of
+    /// `entries` (pairs of name and size).
+    pub fn check_entries<'a, I>(&self, entries: I) -> Result<()>
+        where I: IntoIterator<Item = (&'a str, u64)>,
+              I::IntoIter: ExactSizeIterator {
+        let entries = entries.into_iter();
+        self.check_entry_count(entries.len())?;
+
+        let mut total = 0u64;
+        for (name, size) in entries {
+            self.check_entry_size(name, size)?;
+            total = total.saturating_add(size);
+            self.check_total_size(total)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unlimited_accepts_everything() {
+        let limits = Limits::unlimited();
+        assert!(limits.check_entries(vec![("a", u64::MAX), ("b", u64::MAX)]).is_ok());
+    }
+
+    #[test]
+    fn check_entries() {
+        let limits = Limits {
+            max_entries: Some(2),
+            max_entry_size: Some(10),
+            max_total_size: Some(15),
+        };
+
+        assert!(limits.check_entries(vec![("a", 10), ("b", 5)]).is_ok());
+        assert_matches!(limits.check_entries(vec![("a", 1), ("b", 1), ("c", 1)]), Err(Error::TooManyEntries { count: 3, max: 2 }));
+        assert_matches!(limits.check_entries(vec![("a", 11)]), Err(Error::EntryTooLarge { size: 11, max: 10, .. }));
+        assert_matches!(limits.check_entries(vec![("a", 10), ("b", 6)]), Err(Error::TotalSizeTooLarge { size: 16, max: 15 }));
+    }
+}
@@ -0,0 +1,278 @@
+//! Checks for entry names that are legal in an archive but cause trouble
+//! once extracted or loaded by the game.
+//!
+//! Start with `lint_names`.
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+
+use ::EntryInfoTable;
+use ::archive_set::normalize_name;
+
+/// A single problematic entry name (or set of names).
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum NameIssue {
+    /// The name uses both `\` and `/` as separators.
+    MixedSeparators {
+        name: String,
+    },
+
+    /// The name contains characters outside of ASCII.
+    NonAscii {
+        name: String,
+    },
+
+    /// The name contains control characters (tabs, newlines, etc).
+    ControlCharacters {
+        name: String,
+    },
+
+    /// The name starts with `\` or `/`.
+    LeadingSeparator {
+        name: String,
+    },
+
+    /// The name has a `..` component.
+    ParentDirectory {
+        name: String,
+    },
+
+    /// Several records of the table have exactly this name. Only the last
+    /// of them can be looked up (or extracted) by name.
+    Duplicate {
+        name: String,
+        count: usize,
+    },
+
+    /// The names differ only by case.
+    CaseDuplicate {
+        names: Vec<String>,
+    },
+
+    /// The names would be extracted to the same path on a case-insensitive
+    /// filesystem.
+    ExtractCollision {
+        names: Vec<String>,
+    },
+
+    /// One name would be extracted to a file at a path another needs to be a
+    /// directory, on any filesystem.
+    FileDirectoryCollision {
+        names: Vec<String>,
+    },
+}
+
+impl NameIssue {
+    /// A short, stable identifier for the kind of issue (for scripts).
+    pub fn code(&self) -> &'static str {
+        match *self {
+            NameIssue::MixedSeparators { .. } => "mixed-separators",
+            NameIssue::NonAscii { .. } => "non-ascii",
+            NameIssue::ControlCharacters { .. } => "control-characters",
+            NameIssue::LeadingSeparator { .. } => "leading-separator",
+            NameIssue::ParentDirectory { .. } => "parent-directory",
+            NameIssue::Duplicate { .. } => "duplicate",
+            NameIssue::CaseDuplicate { .. } => "case-duplicate",
+            NameIssue::ExtractCollision { .. } => "extract-collision",
+            NameIssue::FileDirectoryCollision { .. } => "file-directory-collision",
+        }
+    }
+
+    /// The entry names this issue is about.
+    pub fn names(&self) -> Vec<&str> {
+        match *self {
+            NameIssue::MixedSeparators { ref name }
+            | NameIssue::NonAscii { ref name }
+            | NameIssue::ControlCharacters { ref name }
+            | NameIssue::LeadingSeparator { ref name }
+            | NameIssue::ParentDirectory { ref name }
+            | NameIssue::Duplicate { ref name, .. } => vec![name.as_str()],
+            NameIssue::CaseDuplicate { ref names }
+            | NameIssue::ExtractCollision { ref names }
+            | NameIssue::FileDirectoryCollision { ref names } => names.iter().map(|n| n.as_str()).collect(),
+        }
+    }
+}
+
+impl fmt::Display for NameIssue {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let description = match *self {
+            NameIssue::MixedSeparators { .. } => "uses both '\\' and '/' as separators",
+            NameIssue::NonAscii { .. } => "contains non-ASCII characters",
+            NameIssue::ControlCharacters { .. } => "contains control characters",
+            NameIssue::LeadingSeparator { .. } => "starts with a separator",
+            NameIssue::ParentDirectory { .. } => "contains a '..' component",
+            NameIssue::Duplicate { .. } => "is the name of more than one record",
+            NameIssue::CaseDuplicate { .. } => "differ only by case",
+            NameIssue::ExtractCollision { .. } => "collide when extracted to a case-insensitive filesystem",
+            NameIssue::FileDirectoryCollision { .. } => "need the same path to be both a file and a directory",
+        };
+
+        write!(f, "{} {}", self.names().join(", "), description)
+    }
+}
+
+/// Find entry names in `table` that are likely to cause problems.
+///
+/// Every record is checked, including those shadowed by a later record of the
+/// same name. Issues about a single name come first, in table order and once
+/// per name, followed by issues about groups of names sorted by name.
+pub fn lint_names(table: &EntryInfoTable) -> Vec<NameIssue> {
+    let records = table.records();
+    let mut entries = table.values().collect::<Vec<_>>();
+    entries.sort_by_key(|e| e.index);
+
+    let mut issues = vec![];
+    let mut checked = HashSet::new();
+
+    for record in &records {
+        let name = &record.name;
+        if !checked.insert(name.as_str()) {
+            continue;
+        }
+
+        if name.contains('\\') && name.contains('/') {
+            issues.push(NameIssue::MixedSeparators { name: name.clone() });
+        }
+
+        if !name.is_ascii() {
+            issues.push(NameIssue::NonAscii { name: name.clone() });
+        }
+
+        if name.chars().any(|c| c.is_control()) {
+            issues.push(NameIssue::ControlCharacters { name: name.clone() });
+        }
+
+        if name.starts_with(&['\\', '/'][..]) {
+            issues.push(NameIssue::LeadingSeparator { name: name.clone() });
+        }
+
+        if name.split(&['\\', '/'][..]).any(|c| c == "..") {
+            issues.push(NameIssue::ParentDirectory { name: name.clone() });
+        }
+    }
+
+    let mut by_lowercase: HashMap<String, Vec<String>> = HashMap::new();
+    let mut by_path: HashMap<String, Vec<String>> = HashMap::new();
+
+    for entry in &entries {
+        by_lowercase.entry(entry.name.to_lowercase()).or_default().push(entry.name.clone());
+        by_path.entry(normalize_name(&entry.name)).or_default().push(entry.name.clone());
+    }
+
+    let mut groups = vec![];
+
+    // Every shadowed record is hidden by another one of the same name.
+    let mut shadowed: HashMap<&str, usize> = HashMap::new();
+    for entry in table.shadowed() {
+        *shadowed.entry(entry.name.as_str()).or_default() += 1;
+    }
+
+    groups.extend(shadowed.into_iter().map(|(name, count)| NameIssue::Duplicate {
+        name: name.to_string(),
+        count: count + 1,
+    }));
+
+    for (_, mut names) in by_lowercase {
+        if names.len() > 1 {
+            names.sort();
+            groups.push(NameIssue::CaseDuplicate { names });
+        }
+    }
+
+    // Names that are already case duplicates of each other are not reported again.
+    for names in by_path.values() {
+        let first = names[0].to_lowercase();
+        if names.iter().any(|n| n.to_lowercase() != first) {
+            let mut names = names.clone();
+            names.sort();
+            groups.push(NameIssue::ExtractCollision { names });
+        }
+    }
+
+    // An entry named `a` can not be extracted alongside an entry named `a\b`.
+    for (path, files) in &by_path {
+        let mut dir = path.as_str();
+        while let Some(idx) = dir.rfind('\\') {
+            dir = &dir[..idx];
+
+            if let Some(dir_names) = by_path.get(dir) {
+                let mut names = dir_names.iter().chain(files).cloned().collect::<Vec<_>>();
+                names.sort();
+                groups.push(NameIssue::FileDirectoryCollision { names });
+            }
+        }
+    }
+
+    groups.sort_by(|a, b| a.names().cmp(&b.names()));
+    issues.extend(groups);
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::EntryInfo;
+
+    fn table(names: &[&str]) -> EntryInfoTable {
+        names.iter()
+            .enumerate()
+            .map(|(index, &name)| EntryInfo { offset: 0, len: 0, name: name.to_string(), index })
+            .collect()
+    }
+
+    #[test]
+    fn lint_names_clean() {
+        let table = table(&["Data\\INI\\a.ini", "Data\\INI\\b.ini", "Art\\Textures\\a.tga"]);
+        assert!(lint_names(&table).is_empty());
+    }
+
+    #[test]
+    fn lint_names_single() {
+        let table = table(&["Data/INI\\a.ini", "Data\\é.ini", "a\tb", "\\abs.ini", "Data\\..\\x.ini"]);
+
+        assert_eq!(lint_names(&table), vec![
+            NameIssue::MixedSeparators { name: "Data/INI\\a.ini".into() },
+            NameIssue::NonAscii { name: "Data\\é.ini".into() },
+            NameIssue::ControlCharacters { name: "a\tb".into() },
+            NameIssue::LeadingSeparator { name: "\\abs.ini".into() },
+            NameIssue::ParentDirectory { name: "Data\\..\\x.ini".into() },
+        ]);
+    }
+
+    #[test]
+    fn lint_names_groups() {
+        let table = table(&["Data\\a.ini", "DATA\\A.INI", "Art\\b.tga", "art/B.tga", "Maps", "maps\\m.map"]);
+
+        assert_eq!(lint_names(&table), vec![
+            NameIssue::ExtractCollision { names: vec!["Art\\b.tga".into(), "art/B.tga".into()] },
+            NameIssue::CaseDuplicate { names: vec!["DATA\\A.INI".into(), "Data\\a.ini".into()] },
+            NameIssue::FileDirectoryCollision { names: vec!["Maps".into(), "maps\\m.map".into()] },
+        ]);
+    }
+
+    #[test]
+    fn lint_names_shadowed_records() {
+        let table = table(&["a\tb", "ok.ini", "a\tb"]);
+        assert_eq!(table.len(), 2);
+
+        assert_eq!(lint_names(&table), vec![
+            NameIssue::ControlCharacters { name: "a\tb".into() },
+            NameIssue::Duplicate { name: "a\tb".into(), count: 2 },
+        ]);
+    }
+
+    #[test]
+    fn lint_names_exact_duplicates() {
+        let table = table(&["b.ini", "a.ini", "b.ini", "A.INI", "b.ini"]);
+
+        let issues = lint_names(&table);
+        assert_eq!(issues, vec![
+            NameIssue::CaseDuplicate { names: vec!["A.INI".into(), "a.ini".into()] },
+            NameIssue::Duplicate { name: "b.ini".into(), count: 3 },
+        ]);
+        assert_eq!(issues[1].code(), "duplicate");
+        assert_eq!(issues[1].to_string(), "b.ini is the name of more than one record");
+    }
+}
@@ -0,0 +1,264 @@
+//! Per-entry filesystem metadata (timestamps and permissions).
+//!
+//! BIG archives do not store this information so unpacking then repacking
+//! loses it. When requested the packer stores a `Manifest` in the archive's
+//! secret data which the unpacker can later restore.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::path::Path;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ::{Error, Result};
+use ::secret_data;
+
+/// Metadata of a single entry as it was on disk at pack time.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct EntryMetadata {
+    /// Modification time in seconds since the Unix epoch.
+    pub modified: Option<u64>,
+
+    /// Unix permission bits (e.g. `0o755`).
+    ///
+    /// Only the read, write, and execute bits are stored and restored: a
+    /// setuid or setgid bit from an archive is never applied.
+    pub mode: Option<u32>,
+}
+
+impl EntryMetadata {
+    /// Capture the metadata we care about from `md`.
+    pub fn from_fs(md: &fs::Metadata) -> Self {
+        EntryMetadata {
+            modified: md.modified().ok().and_then(unix_secs),
+            mode: fs_mode(md),
+        }
+    }
+
+    /// Restore this metadata onto the file at `path`.
+    ///
+    /// Permission bits are only restored on Unix.
+    pub fn apply<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+
+        if let Some(modified) = self.modified {
+            let f = File::options().write(true).open(path)
+                .map_err(|e| Error::io(e, "opening", path))?;
+            f.set_modified(UNIX_EPOCH + Duration::from_secs(modified))
+                .map_err(|e| Error::io(e, "setting the modification time of", path))?;
+        }
+
+        // NOTE: This must happen last as the mode may make the file read-only.
+        if let Some(mode) = self.mode {
+            set_fs_mode(path, mode)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The permission bits that are stored and restored.
+const MODE_MASK: u32 = 0o777;
+
+#[cfg(unix)]
+fn fs_mode(md: &fs::Metadata) -> Option<u32> {
+    use std::os::unix::fs::PermissionsExt;
+    Some(md.permissions().mode() & MODE_MASK)
+}
+
+#[cfg(not(unix))]
+fn fs_mode(_md: &fs::Metadata) -> Option<u32> {
+    None
+}
+
+#[cfg(unix)]
+fn set_fs_mode(path: &Path, mode: u32) -> Result<()> {
+    use std::os::unix::fs::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(mode & MODE_MASK))
+        .map_err(|e| Error::io(e, "setting the permissions of", path))
+}
+
+#[cfg(not(unix))]
+fn set_fs_mode(_path: &Path, _mode: u32) -> Result<()> {
+    Ok(())
+}
+
+/// Metadata for every entry in an archive, keyed by entry name.
+///
+/// Serialized as UTF-8 lines of `modified<TAB>mode<TAB>name` where a missing
+/// value is written as `-` and `mode` is octal. Every byte of `%` and control
+/// characters (such as tabs and newlines) in `name` is written as `%XX`.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    entries: HashMap<String, EntryMetadata>,
+}
+
+impl Manifest {
+    /// The `secret_data` section tag a manifest is stored under.
+    pub const SECTION_TAG: [u8; 4] = *b"EZMD";
+
+    pub fn new() -> Self {
+        Manifest::default()
+    }
+
+    pub fn insert(&mut self, name: String, metadata: EntryMetadata) {
+        self.entries.insert(name, metadata);
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EntryMetadata> {
+        self.entries.get(name)
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Find and parse a manifest in an archive's secret data.
+    pub fn from_secret_data(data: &[u8]) -> Option<Manifest> {
+        secret_data::find_section(data, &Self::SECTION_TAG)
+            .and_then(Manifest::from_bytes)
+    }
+
+    /// Parse a serialized manifest, returning `None` if it is malformed.
+    pub fn from_bytes(bytes: &[u8]) -> Option<Manifest> {
+        let text = ::std::str::from_utf8(bytes).ok()?;
+        let mut manifest = Manifest::new();
+
+        for line in text.lines() {
+            let mut parts = line.splitn(3, '\t');
+            let modified = parse_field(parts.next()?, 10)?;
+            let mode = parse_field(parts.next()?, 8)?;
+            let name = unescape_name(parts.next()?)?;
+
+            manifest.insert(name, EntryMetadata {
+                modified,
+                mode: mode.map(|m| m as u32),
+            });
+        }
+
+        Some(manifest)
+    }
+
+    /// Serialize this manifest, sorted by entry name so output is reproducible.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut names = self.entries.keys().collect::<Vec<_>>();
+        names.sort();
+
+        let mut text = String::new();
+        for name in names {
+            let md = &self.entries[name];
+            let modified = md.modified.map(|m| m.to_string()).unwrap_or_else(|| String::from("-"));
+            let mode = md.mode.map(|m| format!("{:o}", m)).unwrap_or_else(|| String::from("-"));
+            text.push_str(&format!("{}\t{}\t{}\n", modified, mode, escape_name(name)));
+        }
+
+        text.into_bytes()
+    }
+}
+
+fn parse_field(field: &str, radix: u32) -> Option<Option<u64>> {
+    if field == "-" {
+        Some(None)
+    } else {
+        u64::from_str_radix(field, radix).ok().map(Some)
+    }
+}
+
+fn escape_name(name: &str) -> String {
+    let mut escaped = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c == '%' || c.is_control() {
+            for b in c.to_string().bytes() {
+                escaped.push_str(&format!("%{:02X}", b));
+            }
+        } else {
+            escaped.push(c);
+        }
+    }
+
+    escaped
+}
+
+fn unescape_name(escaped: &str) -> Option<String> {
+    let mut bytes = Vec::with_capacity(escaped.len());
+    let mut rest = escaped.as_bytes();
+
+    while let Some((&b, tail)) = rest.split_first() {
+        if b == b'%' {
+            let hex = ::std::str::from_utf8(tail.get(..2)?).ok()?;
+            bytes.push(u8::from_str_radix(hex, 16).ok()?);
+            rest = &tail[2..];
+        } else {
+            bytes.push(b);
+            rest = tail;
+        }
+    }
+
+    String::from_utf8(bytes).ok()
+}
+
+/// Seconds since the Unix epoch for `time`, if it is not before the epoch.
+fn unix_secs(time: SystemTime) -> Option<u64> {
+    time.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn manifest_round_trip() {
+        let mut manifest = Manifest::new();
+        manifest.insert("a/b.ini".into(), EntryMetadata { modified: Some(1_500_000_000), mode: Some(0o755) });
+        manifest.insert("c d.txt".into(), EntryMetadata { modified: None, mode: None });
+
+        let bytes = manifest.to_bytes();
+        assert_eq!(bytes, b"1500000000\t755\ta/b.ini\n-\t-\tc d.txt\n".to_vec());
+        assert_eq!(Manifest::from_bytes(&bytes), Some(manifest));
+    }
+
+    #[test]
+    fn manifest_escapes_names() {
+        let mut manifest = Manifest::new();
+        for name in &["a\nb.ini", "tab\there", "50%.ini", "\u{85}next-line", "Data\\INI\\é.ini"] {
+            manifest.insert(name.to_string(), EntryMetadata { modified: Some(1), mode: None });
+        }
+
+        let bytes = manifest.to_bytes();
+        assert_eq!(bytes.iter().filter(|&&b| b == b'\n').count(), 5);
+        assert!(String::from_utf8_lossy(&bytes).contains("1\t-\ta%0Ab.ini\n"));
+        assert_eq!(Manifest::from_bytes(&bytes), Some(manifest));
+
+        assert_eq!(Manifest::from_bytes(b"-\t-\ta%0\n"), None);
+        assert_eq!(Manifest::from_bytes(b"-\t-\ta%zzb\n"), None);
+    }
+
+    #[test]
+    fn manifest_malformed() {
+        assert_eq!(Manifest::from_bytes(b"nope\t-\tname\n"), None);
+        assert_eq!(Manifest::from_bytes(b"-\t-\n"), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn apply_masks_special_mode_bits() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let path = ::std::env::temp_dir().join(format!("easage-metadata-mode-{}", ::std::process::id()));
+        fs::write(&path, b"a").unwrap();
+
+        EntryMetadata { modified: None, mode: Some(0o4755) }.apply(&path).unwrap();
+        let md = fs::metadata(&path).unwrap();
+        assert_eq!(md.permissions().mode() & 0o7777, 0o755);
+        assert_eq!(EntryMetadata::from_fs(&md).mode, Some(0o755));
+
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o2644)).unwrap();
+        assert_eq!(EntryMetadata::from_fs(&fs::metadata(&path).unwrap()).mode, Some(0o644));
+
+        fs::remove_file(&path).unwrap();
+    }
+}
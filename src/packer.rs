@@ -1,13 +1,21 @@
-use std::fs::File;
+use std::borrow::Cow;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
+use std::fs::{File, Metadata};
 use std::io::{self, Read, Write};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::mem;
 
 use walkdir::WalkDir;
 use byteorder::{BigEndian, LittleEndian, WriteBytesExt};
 
-use ::{Result, Error, Archive, Kind};
+use ::{Result, Error, Archive, EntryInfo, Format, IoOptions, Kind, Limits};
+use ::crc32;
+use ::checksums::Checksums;
+use ::metadata::{EntryMetadata, Manifest};
+use ::secret_data::{self, Section};
 
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum EntryOrderCriteria {
     SmallestToLargest,
     Path,
@@ -17,114 +25,831 @@ pub struct Settings {
     pub entry_order_criteria: EntryOrderCriteria,
     pub strip_prefix: Option<String>,
     pub kind: Kind,
+
+    /// Store each file's modification time and permissions in the archive's
+    /// secret data so they can be restored when unpacking.
+    pub store_metadata: bool,
+
+    /// Store the CRC-32 of each entry in the archive's secret data so
+    /// corruption can be detected (see `ArchiveBuilder::checksums`).
+    pub store_checksums: bool,
+
+    /// Checked against the files found on disk before any of them are read.
+    pub limits: Limits,
+
+    /// The separator used in entry names regardless of the platform's own,
+    /// so packing the same directory produces the same archive everywhere.
+    pub separator: Separator,
+
+    /// Names of entries to write first, in this order, e.g. the table order
+    /// of a previous version of the archive. The remaining files follow,
+    /// ordered by `entry_order_criteria`; names without a file are ignored.
+    pub preferred_order: Vec<String>,
+
+    /// Written to the start of the secret data, before anything easage stores
+    /// there itself. Metadata and checksum sections in it are dropped as they
+    /// would no longer match the entries.
+    ///
+    /// If this is not made up of `secret_data` sections, metadata and
+    /// checksums stored after it can not be found again.
+    pub secret_data: Vec<u8>,
+
+    /// Add a zero-length entry for every directory below the packed one, named
+    /// after it with a trailing `separator`, so that empty directories survive
+    /// being packed and unpacked (see `EntryInfo::is_directory_marker`).
+    pub directory_markers: bool,
+
+    /// Make `pack_incremental` read every file whose size matches its entry
+    /// in the base archive and compare their CRC-32s, even if the
+    /// modification time matches too.
+    pub compare_contents: bool,
+
+    /// How source files are read.
+    pub io: IoOptions,
 }
 
-/// Recursively walk a given directory and pack *all* files into an `Archive`.
-pub fn pack_directory<P>(directory: P, settings: Settings) -> Result<Archive>
-    where P: AsRef<Path> {
-    let directory = directory.as_ref();
-    let mut entries: Vec<(String, Vec<u8>)> = vec![];
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            entry_order_criteria: EntryOrderCriteria::Path,
+            strip_prefix: None,
+            kind: Kind::BigF,
+            store_metadata: false,
+            store_checksums: false,
+            limits: Limits::default(),
+            separator: Separator::Backslash,
+            preferred_order: vec![],
+            secret_data: vec![],
+            directory_markers: false,
+            compare_contents: false,
+            io: IoOptions::default(),
+        }
+    }
+}
+
+/// A file (or, with `Settings::directory_markers`, a directory) on disk that
+/// will become an entry.
+struct SourceFile {
+    name: String,
+    path: PathBuf,
+    md: Metadata,
+}
+
+impl SourceFile {
+    fn is_directory(&self) -> bool {
+        self.md.is_dir()
+    }
+
+    /// The length of the entry's data.
+    fn len(&self) -> u64 {
+        if self.is_directory() { 0 } else { self.md.len() }
+    }
+
+    fn read(&self, io: &IoOptions) -> Result<Vec<u8>> {
+        if self.is_directory() {
+            return Ok(vec![]);
+        }
+
+        let mut f = File::open(&self.path)
+            .map_err(|e| Error::io(e, "opening", &self.path))?;
+        io.advise_file(&f);
+        let mut buf = Vec::with_capacity(self.md.len() as usize);
+        let _len_read = f.read_to_end(&mut buf)
+            .map_err(|e| Error::io(e, "reading", &self.path))?;
+        Ok(buf)
+    }
+}
+
+/// Recursively walk `directory` and name each file, sorted according to `settings`.
+///
+/// This fails if the files exceed `settings.limits`.
+fn collect_files(directory: &Path, settings: &Settings) -> Result<Vec<SourceFile>> {
+    let mut files = vec![];
 
     for fs_item in WalkDir::new(directory) {
         let fs_item = fs_item?;
         let md = fs_item.metadata()?;
-        if md.is_dir() {
+        if md.is_dir() && (!settings.directory_markers || fs_item.depth() == 0) {
             continue;
         }
 
-        let path = fs_item.path();
-        let source_path = path.to_path_buf();
-        let mut name = source_path.to_string_lossy().to_string();
+        let path = fs_item.path().to_path_buf();
+        let mut name = path.to_string_lossy().to_string();
 
         if let Some(ref strip_prefix) = settings.strip_prefix {
-            name = name.trim_left_matches(strip_prefix).to_string();
+            name = name.trim_start_matches(strip_prefix.as_str()).to_string();
         }
 
-        let mut f = File::open(source_path)?;
-        let mut buf = Vec::with_capacity(md.len() as usize);
-        let _len_read = f.read_to_end(&mut buf)?;
+        if md.is_dir() {
+            name.push('/');
+        }
 
-        entries.push((name, buf));
+        name = settings.separator.apply(&name);
+
+        files.push(SourceFile { name, path, md });
     }
 
     match settings.entry_order_criteria {
-        EntryOrderCriteria::SmallestToLargest => entries.sort_by(|a, b| a.1.len().cmp(&b.1.len())),
-        EntryOrderCriteria::Path => entries.sort_by(|a, b| a.0.cmp(&b.0)),
+        EntryOrderCriteria::SmallestToLargest => files.sort_by(|a, b| (a.len(), &a.name).cmp(&(b.len(), &b.name))),
+        EntryOrderCriteria::Path => files.sort_by(|a, b| a.name.cmp(&b.name)),
+    };
+
+    if !settings.preferred_order.is_empty() {
+        let positions = settings.preferred_order.iter()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect::<HashMap<_, _>>();
+
+        // Stable, so files not in `preferred_order` keep the order above.
+        files.sort_by_key(|f| positions.get(f.name.as_str()).cloned().unwrap_or(positions.len()));
+    }
+
+    settings.limits.check_entries(files.iter().map(|f| (f.name.as_str(), f.len())))?;
+
+    Ok(files)
+}
+
+fn manifest_secret_data(manifest: &Manifest) -> Vec<u8> {
+    let manifest_bytes = manifest.to_bytes();
+    secret_data::write_sections(&[Section { tag: Manifest::SECTION_TAG, payload: &manifest_bytes }])
+}
+
+/// `settings.secret_data` without stale easage sections, followed by `manifest`.
+///
+/// Checksums are appended later by `ArchiveBuilder::build`.
+fn secret_data_for(settings: &Settings, manifest: Option<&Manifest>) -> Vec<u8> {
+    let sections = secret_data::read_sections(&settings.secret_data);
+    let mut data = if sections.is_empty() {
+        settings.secret_data.clone()
+    } else {
+        let kept = sections.into_iter()
+            .filter(|s| s.tag != Manifest::SECTION_TAG && s.tag != Checksums::SECTION_TAG)
+            .collect::<Vec<_>>();
+
+        secret_data::write_sections(&kept)
     };
 
-    let entries = entries
-        .iter()
-        .map(|&(ref name, ref data)| (name.as_str(), data.as_slice()))
+    if let Some(manifest) = manifest {
+        data.extend(manifest_secret_data(manifest));
+    }
+
+    data
+}
+
+/// Recursively walk a given directory and pack *all* files into an `Archive`.
+///
+/// Directories are not stored unless `settings.directory_markers` is set.
+/// Metadata is never stored for directory markers.
+///
+/// The result depends only on the files' names and contents (and, with
+/// `store_metadata`, their metadata) and `settings`: entries are ordered by
+/// comparing names byte-wise (with ties broken by name when ordering by size)
+/// and named with `settings.separator`, so the same input produces a
+/// byte-identical archive on every platform.
+pub fn pack_directory<P>(directory: P, settings: Settings) -> Result<Archive>
+    where P: AsRef<Path> {
+    let files = collect_files(directory.as_ref(), &settings)?;
+    let mut builder = ArchiveBuilder::new(settings.kind);
+    builder.checksums(settings.store_checksums);
+    let mut manifest = Manifest::new();
+
+    for file in files {
+        if settings.store_metadata && !file.is_directory() {
+            manifest.insert(file.name.clone(), EntryMetadata::from_fs(&file.md));
+        }
+
+        let data = file.read(&settings.io)?;
+        builder.add_owned(file.name, data);
+    }
+
+    let manifest = if settings.store_metadata { Some(&manifest) } else { None };
+    builder.secret_data(secret_data_for(&settings, manifest));
+
+    builder.build()
+}
+
+/// An entry `pack_directory` would write.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PlannedEntry {
+    pub name: String,
+    pub path: PathBuf,
+    pub size: u64,
+}
+
+/// What `pack_directory` would write, see `plan_directory`.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct PackPlan {
+    /// The entries in the order they would be written.
+    pub entries: Vec<PlannedEntry>,
+
+    /// The size of the archive that would be created.
+    pub size: SizeReport,
+}
+
+/// The size in bytes of each part of an archive.
+#[derive(Debug, Copy, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct SizeReport {
+    /// Always `Archive::HEADER_LEN`.
+    pub header: u64,
+
+    /// The table: 8 bytes plus the UTF-8 name and a NUL terminator per entry.
+    pub table: u64,
+
+    pub secret_data: u64,
+
+    /// The data of every entry.
+    pub data: u64,
+
+    /// Zeroes written between entries to align their data.
+    pub padding: u64,
+}
+
+impl SizeReport {
+    /// The offset at which entry data (or padding) starts.
+    pub fn data_start(&self) -> u64 {
+        self.header + self.table + self.secret_data
+    }
+
+    /// The size of the whole archive.
+    pub fn total(&self) -> u64 {
+        self.data_start() + self.padding + self.data
+    }
+}
+
+/// Determine which entries `pack_directory` would write with the same
+/// arguments, and the size of the resulting archive, without reading the
+/// contents of any file.
+pub fn plan_directory<P>(directory: P, settings: &Settings) -> Result<PackPlan>
+    where P: AsRef<Path> {
+    let files = collect_files(directory.as_ref(), settings)?;
+
+    let secret_data_len = if settings.store_metadata {
+        let mut manifest = Manifest::new();
+        for file in files.iter().filter(|f| !f.is_directory()) {
+            manifest.insert(file.name.clone(), EntryMetadata::from_fs(&file.md));
+        }
+
+        secret_data_for(settings, Some(&manifest)).len()
+    } else {
+        secret_data_for(settings, None).len()
+    };
+
+    let secret_data_len = if settings.store_checksums {
+        secret_data_len + secret_data::SECTION_HEADER_LEN + Checksums::serialized_len(files.iter().map(|f| f.name.as_str()))
+    } else {
+        secret_data_len
+    };
+
+    let entries = files.into_iter()
+        .map(|file| PlannedEntry { size: file.len(), name: file.name, path: file.path })
         .collect::<Vec<_>>();
 
-    let archive = pack(entries, settings.kind)?;
-    Ok(archive)
+    let size = size_report(entries.iter().map(|e| (e.name.as_str(), e.size)), secret_data_len as u64, 1);
+
+    Ok(PackPlan { entries, size })
+}
+
+/// The size of the archive `pack` would create from entries with the given
+/// names and sizes, `settings.secret_data`, and the checksums `settings` asks for.
+///
+/// Nothing is read. The metadata stored by `Settings::store_metadata`
+/// depends on the files themselves so it is only accounted for by
+/// `estimate_directory`.
+pub fn estimate<'a, I>(entries: I, settings: &Settings) -> SizeReport
+    where I: IntoIterator<Item = (&'a str, u64)> {
+    let entries = entries.into_iter().collect::<Vec<_>>();
+
+    let secret_data_len = secret_data_for(settings, None).len();
+    let secret_data_len = if settings.store_checksums {
+        secret_data_len + secret_data::SECTION_HEADER_LEN + Checksums::serialized_len(entries.iter().map(|&(name, _)| name))
+    } else {
+        secret_data_len
+    };
+
+    size_report(entries, secret_data_len as u64, 1)
+}
+
+/// The size of the archive `pack_directory` would create without reading the
+/// contents of any file. See `plan_directory` for the entries as well.
+pub fn estimate_directory<P>(directory: P, settings: &Settings) -> Result<SizeReport>
+    where P: AsRef<Path> {
+    plan_directory(directory, settings).map(|plan| plan.size)
+}
+
+/// The size of an archive containing entries with the given names and sizes,
+/// with the data of every non-empty entry aligned to `alignment` bytes.
+fn size_report<'a, I>(entries: I, secret_data_len: u64, alignment: u32) -> SizeReport
+    where I: IntoIterator<Item = (&'a str, u64)> {
+    let mut report = SizeReport {
+        header: u64::from(Archive::HEADER_LEN),
+        secret_data: secret_data_len,
+        ..SizeReport::default()
+    };
+
+    let mut sizes = vec![];
+    for (name, size) in entries {
+        report.table += (mem::size_of::<u32>() + // offset
+            mem::size_of::<u32>() + // length
+            name.len() + 1) as u64; // name + null
+
+        sizes.push(size);
+    }
+
+    // Padding depends on where the data starts so it can only be determined
+    // once the table size is known.
+    let alignment = u64::from(cmp::max(alignment, 1));
+    let mut end = report.data_start();
+    for size in sizes {
+        if size > 0 {
+            let offset = end.div_ceil(alignment) * alignment;
+            report.padding += offset - end;
+            end = offset;
+        }
+
+        report.data += size;
+        end += size;
+    }
+
+    report
+}
+
+/// Like `pack_directory` but reuse the data of entries in `base` that have not
+/// changed on disk instead of reading them again.
+///
+/// A file is considered unchanged if its size matches the entry in `base` and either:
+///
+/// * `base` was packed with `Settings::store_metadata` and the modification time
+///   matches, in which case the file is not read at all, or
+/// * the CRC-32 of the file matches that of the entry (as stored by
+///   `Settings::store_checksums`, or else computed from `base`)
+///
+/// Like `make`, this misses files that were changed without changing their size
+/// or modification time. `Settings::compare_contents` only skips reading files
+/// whose size changed.
+///
+/// Files that no longer exist on disk are not carried over from `base`.
+pub fn pack_incremental<P>(base: &Archive, directory: P, settings: Settings) -> Result<Archive>
+    where P: AsRef<Path> {
+    let base_table = base.read_entry_metadata_table()?;
+    let base_secret_data = base.read_secret_data(&base_table)?;
+    let base_manifest = base_secret_data.and_then(Manifest::from_secret_data);
+    let base_checksums = base_secret_data.and_then(Checksums::from_secret_data);
+
+    let files = collect_files(directory.as_ref(), &settings)?;
+    let mut builder = ArchiveBuilder::new(settings.kind);
+    builder.checksums(settings.store_checksums);
+    let mut manifest = Manifest::new();
+
+    for file in files {
+        let md = EntryMetadata::from_fs(&file.md);
+        if settings.store_metadata && !file.is_directory() {
+            manifest.insert(file.name.clone(), md);
+        }
+
+        let base_entry = base_table.get(&file.name)
+            .filter(|e| u64::from(e.len) == file.len());
+
+        let base_entry = match base_entry {
+            Some(base_entry) => base_entry,
+            None => {
+                let data = file.read(&settings.io)?;
+                builder.add_owned(file.name, data);
+                continue;
+            },
+        };
+
+        let is_unchanged_by_mtime = !settings.compare_contents && md.modified.is_some()
+            && base_manifest.as_ref()
+                .and_then(|m| m.get(&file.name))
+                .map(|base_md| base_md.modified == md.modified)
+                .unwrap_or(false);
+
+        if is_unchanged_by_mtime {
+            builder.add_raw_from(base, base_entry)?;
+            continue;
+        }
+
+        let data = file.read(&settings.io)?;
+        let base_crc = match base_checksums.as_ref().and_then(|c| c.get(&base_entry.name)) {
+            Some(crc) => crc,
+            None => crc32::checksum(base.get_bytes_via_entry(base_entry)?),
+        };
+
+        if crc32::checksum(&data) == base_crc {
+            builder.add_raw_from(base, base_entry)?;
+        } else {
+            builder.add_owned(file.name, data);
+        }
+    }
+
+    let manifest = if settings.store_metadata { Some(&manifest) } else { None };
+    builder.secret_data(secret_data_for(&settings, manifest));
+
+    builder.build()
+}
+
+/// The separator used between the directories of an entry name.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Separator {
+    /// `\`, as written by the original tools.
+    Backslash,
+
+    /// `/`
+    Slash,
+}
+
+impl Separator {
+    /// Replace every `\\` and `/` in `name` with this separator.
+    pub fn apply(self, name: &str) -> String {
+        match self {
+            Separator::Backslash => name.replace('/', "\\"),
+            Separator::Slash => name.replace('\\', "/"),
+        }
+    }
+}
+
+/// Settings for `repack`.
+///
+/// The default rewrites an archive without changing anything but the
+/// placement of entry data (which will be packed contiguously in table order).
+pub struct RepackSettings {
+    /// Reorder entries, or keep their table order if `None`.
+    ///
+    /// Ties are broken by name so the result is reproducible.
+    pub entry_order_criteria: Option<EntryOrderCriteria>,
+
+    /// Convert to another kind, or keep the source's if `None`.
+    pub kind: Option<Kind>,
+
+    /// Rewrite every separator in entry names to this one, or keep them if `None`.
+    pub separator: Option<Separator>,
+
+    /// Store identical entry data once, shared by every entry that has it.
+    ///
+    /// NOTE: `Archive::validate` reports such entries as overlapping.
+    pub deduplicate: bool,
+
+    /// Start the data of every entry at a multiple of this many bytes (`1` for none).
+    pub alignment: u32,
+
+    /// Drop the source's secret data instead of copying it verbatim.
+    ///
+    /// NOTE: Metadata and checksums stored by easage are keyed by entry name
+    /// so they no longer apply to entries renamed by `separator`.
+    pub strip_secret_data: bool,
+
+    /// How the source archive is read. The output is written as given to
+    /// `repack_to`, see `IoOptions::buffered`.
+    pub io: IoOptions,
+}
+
+impl Default for RepackSettings {
+    fn default() -> Self {
+        RepackSettings {
+            entry_order_criteria: None,
+            kind: None,
+            separator: None,
+            deduplicate: false,
+            alignment: 1,
+            strip_secret_data: false,
+            io: IoOptions::default(),
+        }
+    }
+}
+
+impl RepackSettings {
+    /// Settings that remove all slack from an archive: entries are written
+    /// contiguously in table order, and identical data is stored once.
+    ///
+    /// The kind, names, and secret data are kept.
+    pub fn compact() -> Self {
+        RepackSettings {
+            deduplicate: true,
+            ..RepackSettings::default()
+        }
+    }
+}
+
+/// Rewrite `archive` according to `settings` into a new in-memory `Archive`.
+///
+/// See `repack_to`.
+pub fn repack(archive: &Archive, settings: &RepackSettings) -> Result<Archive> {
+    let mut buf = vec![];
+    repack_to(archive, settings, &mut buf)?;
+    Archive::from_bytes(&buf)
+}
+
+/// Rewrite `archive` according to `settings` directly into `out` in a single
+/// pass, returning the number of bytes written.
+///
+/// Entry data is copied as-is (no decoding) so any compression is preserved.
+///
+/// # Errors
+///
+/// * If changing separators makes two names equal this will return `Err(Error::DuplicateEntry)`
+/// * If `archive` is not in `Format::STANDARD` and `settings.kind` is `None`
+///   this will return `Err(Error::UnsupportedFormat)`
+/// * See `pack` for the other possible errors
+pub fn repack_to<W: Write>(archive: &Archive, settings: &RepackSettings, out: &mut W) -> Result<u64> {
+    settings.io.advise_map(archive.as_slice());
+    let table = archive.table()?;
+    let kind = match settings.kind {
+        Some(kind) => kind,
+        None if archive.format() != Format::STANDARD => {
+            return Err(Error::UnsupportedFormat { magic: archive.read_kind()?.magic().to_vec() });
+        },
+        None => archive.read_kind()?,
+    };
+
+    let secret_data = if settings.strip_secret_data {
+        None
+    } else {
+        archive.read_secret_data(table)?
+    };
+
+    let mut entries = Vec::with_capacity(table.len());
+    for entry in table.values() {
+        let name = match settings.separator {
+            Some(separator) => separator.apply(&entry.name),
+            None => entry.name.clone(),
+        };
+
+        entries.push((name, archive.get_bytes_via_entry(entry)?));
+    }
+
+    match settings.entry_order_criteria {
+        Some(EntryOrderCriteria::SmallestToLargest) => entries.sort_by(|a, b| (a.1.len(), &a.0).cmp(&(b.1.len(), &b.0))),
+        Some(EntryOrderCriteria::Path) => entries.sort_by(|a, b| a.0.cmp(&b.0)),
+        None => {},
+    }
+
+    let entries = entries.iter()
+        .map(|(name, data)| (name.as_str(), *data))
+        .collect::<Vec<_>>();
+
+    let layout = Layout {
+        alignment: settings.alignment,
+        deduplicate: settings.deduplicate,
+    };
+
+    write_archive(out, &entries, kind, secret_data.unwrap_or(&[]), layout)
+}
+
+/// Incrementally assemble an `Archive` from borrowed and owned entry data.
+///
+/// Entries are written in the order they are added.
+#[derive(Debug)]
+pub struct ArchiveBuilder<'a> {
+    kind: Kind,
+    entries: Vec<(String, Cow<'a, [u8]>)>,
+    secret_data: Vec<u8>,
+    limits: Limits,
+    checksums: bool,
+}
+
+impl<'a> ArchiveBuilder<'a> {
+    pub fn new(kind: Kind) -> Self {
+        ArchiveBuilder {
+            kind,
+            entries: vec![],
+            secret_data: vec![],
+            limits: Limits::default(),
+            checksums: false,
+        }
+    }
+
+    /// Add an entry whose data is borrowed (for example from another archive).
+    pub fn add<S: Into<String>>(&mut self, name: S, data: &'a [u8]) -> &mut Self {
+        self.entries.push((name.into(), Cow::Borrowed(data)));
+        self
+    }
+
+    /// Add an entry whose data is owned by the builder.
+    pub fn add_owned<S: Into<String>>(&mut self, name: S, data: Vec<u8>) -> &mut Self {
+        self.entries.push((name.into(), Cow::Owned(data)));
+        self
+    }
+
+    /// Add `entry` by borrowing its bytes directly from `archive` (which `entry` must
+    /// have been read from) without decoding or copying them.
+    ///
+    /// Any encoding of the entry's data (e.g. compression) is preserved as-is.
+    ///
+    /// # Errors
+    ///
+    /// * If the entry's data lies outside of `archive` this will return `Err(Error::EntryOutOfBounds)`
+    pub fn add_raw_from(&mut self, archive: &'a Archive, entry: &EntryInfo) -> Result<&mut Self> {
+        self.add_raw_from_as(archive, entry, entry.name.clone())
+    }
+
+    /// Like `add_raw_from` but give the entry a new `name` in the resulting archive.
+    pub fn add_raw_from_as<S: Into<String>>(&mut self, archive: &'a Archive, entry: &EntryInfo, name: S) -> Result<&mut Self> {
+        let data = archive.get_bytes_via_entry(entry)?;
+        Ok(self.add(name, data))
+    }
+
+    /// Set the data written between the entry metadata table and the first entry's data.
+    pub fn secret_data(&mut self, data: Vec<u8>) -> &mut Self {
+        self.secret_data = data;
+        self
+    }
+
+    /// Append a `Checksums` section for all entries to the secret data when building.
+    ///
+    /// This only makes sense if the secret data is empty or made up of
+    /// `secret_data` sections, otherwise the checksums can not be found again.
+    pub fn checksums(&mut self, store: bool) -> &mut Self {
+        self.checksums = store;
+        self
+    }
+
+    /// Refuse to `build` if the added entries exceed `limits`.
+    ///
+    /// The built archive enforces the same `limits` when its table is read.
+    pub fn limits(&mut self, limits: Limits) -> &mut Self {
+        self.limits = limits;
+        self
+    }
+
+    /// The number of entries added so far.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Write all added entries into a new `Archive`.
+    ///
+    /// See `pack` and `Limits` for the possible errors.
+    pub fn build(&self) -> Result<Archive> {
+        self.limits.check_entries(self.entries.iter().map(|(name, data)| (name.as_str(), data.len() as u64)))?;
+
+        let entries = self.entries.iter()
+            .map(|(name, data)| (name.as_str(), data.as_ref()))
+            .collect::<Vec<_>>();
+
+        let mut secret_data = Cow::Borrowed(&self.secret_data[..]);
+        if self.checksums {
+            let checksums = Checksums::compute(entries.iter().cloned()).to_bytes();
+            let section = secret_data::write_sections(&[Section { tag: Checksums::SECTION_TAG, payload: &checksums }]);
+            secret_data.to_mut().extend(section);
+        }
+
+        pack_with_secret_data(entries, self.kind, &secret_data)
+            .map(|archive| archive.with_limits(self.limits))
+    }
 }
 
 /// Pack the given tuples of `(name, data)` into an `Archive`.
 ///
 /// The `name` / `.0`th item in `entries` *is not* the path on disk.
 /// It is the name that the given entry will have in the output archive.
+///
+/// `Kind::Other` is written as-is. Only tools that opt in (see
+/// `Archive::with_lenient_magic`) will be able to read the result.
+///
+/// # Errors
+///
+/// * If `entries` is empty this will return `Err(Error::AttemptCreateEmpty)`
+/// * If two entries share a name this will return `Err(Error::DuplicateEntry)`
+/// * If the resulting archive would not be addressable with 32-bit offsets
+///   this will return `Err(Error::ArchiveTooLarge)`
 pub fn pack(entries: Vec<(&str, &[u8])>, kind: Kind) -> Result<Archive> {
+    pack_with_secret_data(entries, kind, &[])
+}
+
+/// Like `pack` but writes `secret_data` between the entry metadata table and the
+/// first entry's data.
+///
+/// See the `secret_data` module for the layout easage itself uses in this region.
+pub fn pack_with_secret_data(entries: Vec<(&str, &[u8])>, kind: Kind, secret_data: &[u8]) -> Result<Archive> {
+    let mut buf = vec![];
+    write_archive(&mut buf, &entries, kind, secret_data, Layout::default())?;
+    Archive::from_bytes(&buf)
+}
+
+/// How `write_archive` places entry data after the table.
+#[derive(Debug, Copy, Clone)]
+struct Layout {
+    /// Start the data of every (non-empty) entry at a multiple of this many bytes.
+    alignment: u32,
+
+    /// Write identical data once and point every entry that has it at the same offset.
+    deduplicate: bool,
+}
+
+impl Default for Layout {
+    fn default() -> Self {
+        Layout {
+            alignment: 1,
+            deduplicate: false,
+        }
+    }
+}
+
+/// Write an archive straight to `out`, returning the number of bytes written.
+///
+/// See `pack` for the possible errors. Failing to write to `out` is an
+/// `Error::IO` whose operation is "writing the archive".
+fn write_archive<W: Write>(out: &mut W, entries: &[(&str, &[u8])], kind: Kind, secret_data: &[u8], layout: Layout) -> Result<u64> {
     if entries.is_empty() {
         return Err(Error::AttemptCreateEmpty);
     }
 
-    let table_size = entries.iter().map(|itm| {
-        mem::size_of::<u32>() + // offset
-        mem::size_of::<u32>() + // length
-        itm.0.len() + 1 // name + null
-    }).sum::<usize>();
+    let mut seen_names = HashSet::with_capacity(entries.len());
+    for &(name, _) in entries {
+        if !seen_names.insert(name) {
+            return Err(Error::DuplicateEntry { name: name.to_string() });
+        }
+    }
 
     // NOTE: For some reason FinalBig's `data_start` is 1 byte less than ours.
-    let data_start = (Archive::HEADER_LEN as usize) + table_size;
-    let total_size_of_entries = entries.iter().map(|itm| itm.1.len()).sum::<usize>();
-    let total_archive_size = data_start + total_size_of_entries;
+    let data_start = size_report(entries.iter().map(|&(name, _)| (name, 0)), secret_data.len() as u64, 1).data_start();
 
-    let kind_bytes = match kind {
-        Kind::Big4 => b"BIG4",
-        Kind::BigF => b"BIGF",
-    };
+    // Lay out the data first so the table can be written in a single pass.
+    let alignment = u64::from(cmp::max(layout.alignment, 1));
+    let mut offsets = Vec::with_capacity(entries.len());
+    let mut written = HashMap::new();
+    let mut total_archive_size = data_start;
 
-    let mut buf = Vec::with_capacity(total_archive_size);
+    for &(_, data) in entries {
+        if layout.deduplicate && !data.is_empty() {
+            if let Some(&offset) = written.get(data) {
+                offsets.push(offset);
+                continue;
+            }
+        }
 
-    // Write the header
-    let _ = buf.write(kind_bytes)?;
-    buf.write_u32::<LittleEndian>(total_archive_size as u32)?;
-    buf.write_u32::<BigEndian>(entries.len() as u32)?;
-    buf.write_u32::<BigEndian>(data_start as u32)?;
+        let offset = if data.is_empty() {
+            total_archive_size
+        } else {
+            total_archive_size.div_ceil(alignment) * alignment
+        };
 
-    // Write the entry metadata table
-    let mut last_offset = data_start;
-    let mut last_len = 0usize;
+        if layout.deduplicate && !data.is_empty() {
+            written.insert(data, offset);
+        }
 
-    for entry in &entries {
-        let len = entry.1.len();
-        let offset = last_offset + last_len;
+        offsets.push(offset);
+        total_archive_size = offset + data.len() as u64;
+    }
 
-        let name_bytes = entry.0.as_bytes();
+    if total_archive_size > u64::from(u32::MAX) {
+        return Err(Error::ArchiveTooLarge {
+            size: total_archive_size,
+            max_size: u64::from(u32::MAX),
+        });
+    }
 
-        buf.write_u32::<BigEndian>(offset as u32)?;
-        buf.write_u32::<BigEndian>(len as u32)?;
-        let _ = buf.write(name_bytes)?;
-        let _ = buf.write(&[b'\0'])?;
+    write_layout(out, entries, &offsets, kind, secret_data, data_start, total_archive_size)
+        .map_err(|e| Error::IO { inner: e, operation: String::from("writing the archive") })?;
 
-        last_offset = offset;
-        last_len = len;
+    Ok(total_archive_size)
+}
+
+/// Write the archive `write_archive` laid out.
+fn write_layout<W: Write>(out: &mut W, entries: &[(&str, &[u8])], offsets: &[u64], kind: Kind, secret_data: &[u8], data_start: u64, total_archive_size: u64) -> io::Result<()> {
+    // Write the header
+    out.write_all(&kind.magic())?;
+    out.write_u32::<LittleEndian>(total_archive_size as u32)?;
+    out.write_u32::<BigEndian>(entries.len() as u32)?;
+    out.write_u32::<BigEndian>(data_start as u32)?;
+
+    // Write the entry metadata table
+    for (&(name, data), &offset) in entries.iter().zip(offsets) {
+        out.write_u32::<BigEndian>(offset as u32)?;
+        out.write_u32::<BigEndian>(data.len() as u32)?;
+        out.write_all(name.as_bytes())?;
+        out.write_all(b"\0")?;
     }
 
+    out.write_all(secret_data)?;
+
     // Write the actual data
-    for mut entry in entries {
-        io::copy(&mut entry.1, &mut buf)?;
+    let mut pos = data_start;
+    for (&(_, data), &offset) in entries.iter().zip(offsets) {
+        if offset < pos {
+            // A deduplicated copy of data that was already written.
+            continue;
+        }
+
+        io::copy(&mut io::repeat(0).take(offset - pos), out)?;
+        out.write_all(data)?;
+        pos = offset + data.len() as u64;
     }
 
-    let ret = Archive::from_bytes(&buf)?;
-    Ok(ret)
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ::validation::Level;
 
     #[test]
     fn pack_2_entries() {
@@ -142,7 +867,7 @@ mod tests {
         let res = pack(entries, Kind::BigF);
         assert!(res.is_ok());
 
-        let mut archive = res.unwrap();
+        let archive = res.unwrap();
         let table = archive.read_entry_metadata_table().unwrap();
 
         {
@@ -157,7 +882,7 @@ mod tests {
 
         {
             let res_opt_other_bytes = archive.get_bytes_via_table(&table, "some/other/name.ini");
-            assert_matches!(res_opt_other_bytes, Err(Error::NoSuchEntry));
+            assert_matches!(res_opt_other_bytes, Err(Error::NoSuchEntry { ref name }) if name == "some/other/name.ini");
         }
     }
 
@@ -166,4 +891,529 @@ mod tests {
         let res = pack(vec![], Kind::BigF);
         assert_matches!(res, Err(Error::AttemptCreateEmpty));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn pack_with_secret_data_round_trip() {
+        let data = [0, 1, 2, 3];
+        let entries = vec![("first/entry.txt", &data[..])];
+
+        let archive = pack_with_secret_data(entries, Kind::BigF, b"secret").unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        assert_matches!(archive.read_secret_data(&table), Ok(Some(s)) if s == b"secret");
+        assert_matches!(archive.get_bytes_via_table(&table, "first/entry.txt"), Ok(Some(bytes)) if bytes == data);
+    }
+
+    #[test]
+    fn pack_duplicate_entries() {
+        let data = [0, 1, 2, 3];
+
+        let entries = vec![
+            ("first/entry.txt", &data[..]),
+            ("first/entry.txt", &data[..]),
+        ];
+
+        let res = pack(entries, Kind::BigF);
+        assert_matches!(res, Err(Error::DuplicateEntry { ref name }) if name == "first/entry.txt");
+    }
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = ::std::env::temp_dir().join(format!("easage-packer-{}-{}", name, ::std::process::id()));
+        let _ = ::std::fs::remove_dir_all(&dir);
+        ::std::fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn settings_for(dir: &Path, store_metadata: bool) -> Settings {
+        Settings {
+            strip_prefix: Some(format!("{}{}", dir.display(), ::std::path::MAIN_SEPARATOR)),
+            store_metadata,
+            ..Settings::default()
+        }
+    }
+
+    #[test]
+    fn pack_incremental_detects_changes() {
+        use std::fs;
+
+        let dir = temp_dir("incremental");
+        fs::write(dir.join("same.ini"), b"unchanged").unwrap();
+        fs::write(dir.join("edited.ini"), b"before").unwrap();
+        fs::write(dir.join("removed.ini"), b"gone").unwrap();
+
+        let base = pack_directory(&dir, settings_for(&dir, false)).unwrap();
+
+        fs::write(dir.join("edited.ini"), b"after!").unwrap();
+        fs::write(dir.join("added.ini"), b"new").unwrap();
+        fs::remove_file(dir.join("removed.ini")).unwrap();
+
+        let archive = pack_incremental(&base, &dir, settings_for(&dir, false)).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        assert_eq!(table.len(), 3);
+        assert_matches!(archive.get_bytes_via_table(&table, "same.ini"), Ok(Some(b)) if b == b"unchanged");
+        assert_matches!(archive.get_bytes_via_table(&table, "edited.ini"), Ok(Some(b)) if b == b"after!");
+        assert_matches!(archive.get_bytes_via_table(&table, "added.ini"), Ok(Some(b)) if b == b"new");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pack_incremental_trusts_stored_mtime() {
+        use std::fs::{self, File};
+        use std::time::{Duration, UNIX_EPOCH};
+
+        let dir = temp_dir("incremental-mtime");
+        let path = dir.join("file.ini");
+        let unreadable = dir.join("unreadable.ini");
+        let mtime = UNIX_EPOCH + Duration::from_secs(1_000_000_000);
+
+        fs::write(&path, b"before").unwrap();
+        fs::write(&unreadable, b"secret").unwrap();
+        for p in &[&path, &unreadable] {
+            File::options().write(true).open(p).unwrap().set_modified(mtime).unwrap();
+        }
+
+        let base = pack_directory(&dir, settings_for(&dir, true)).unwrap();
+
+        // Same size and modification time: unchanged files are not read, so
+        // neither new content nor missing permissions are noticed.
+        fs::write(&path, b"after!").unwrap();
+        File::options().write(true).open(&path).unwrap().set_modified(mtime).unwrap();
+        set_readable(&unreadable, false);
+
+        let archive = pack_incremental(&base, &dir, settings_for(&dir, true)).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert_matches!(archive.get_bytes_via_table(&table, "file.ini"), Ok(Some(b)) if b == b"before");
+        assert_matches!(archive.get_bytes_via_table(&table, "unreadable.ini"), Ok(Some(b)) if b == b"secret");
+
+        let manifest = Manifest::from_secret_data(archive.read_secret_data(&table).unwrap().unwrap()).unwrap();
+        assert_eq!(manifest.get("file.ini").unwrap().modified, Some(1_000_000_000));
+
+        // Unless contents are compared.
+        set_readable(&unreadable, true);
+        let settings = Settings { compare_contents: true, ..settings_for(&dir, true) };
+        let archive = pack_incremental(&base, &dir, settings).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert_matches!(archive.get_bytes_via_table(&table, "file.ini"), Ok(Some(b)) if b == b"after!");
+
+        // A different modification time always makes the file be read.
+        File::options().write(true).open(&path).unwrap().set_modified(mtime + Duration::from_secs(1)).unwrap();
+        let archive = pack_incremental(&base, &dir, settings_for(&dir, true)).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert_matches!(archive.get_bytes_via_table(&table, "file.ini"), Ok(Some(b)) if b == b"after!");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[cfg(unix)]
+    fn set_readable(path: &Path, readable: bool) {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = if readable { 0o644 } else { 0o000 };
+        ::std::fs::set_permissions(path, ::std::fs::Permissions::from_mode(mode)).unwrap();
+    }
+
+    // Files can not be made unreadable portably elsewhere, the changed
+    // content that is not picked up shows that files are not read too.
+    #[cfg(not(unix))]
+    fn set_readable(_path: &Path, _readable: bool) {}
+
+    #[test]
+    fn archive_builder_add_raw_from() {
+        let source = pack(vec![
+            ("keep.ini", &b"keep"[..]),
+            ("rename.ini", &b"rename"[..]),
+            ("drop.ini", &b"drop"[..]),
+        ], Kind::BigF).unwrap();
+
+        let table = source.read_entry_metadata_table().unwrap();
+
+        let archive = {
+            let mut builder = ArchiveBuilder::new(Kind::Big4);
+            builder.add_raw_from(&source, &table["keep.ini"]).unwrap();
+            builder.add_raw_from_as(&source, &table["rename.ini"], "renamed.ini").unwrap();
+            builder.build().unwrap()
+        };
+
+        let new_table = archive.read_entry_metadata_table().unwrap();
+        assert_eq!(new_table.len(), 2);
+        assert_matches!(archive.get_bytes_via_table(&new_table, "keep.ini"), Ok(Some(b)) if b == b"keep");
+        assert_matches!(archive.get_bytes_via_table(&new_table, "renamed.ini"), Ok(Some(b)) if b == b"rename");
+    }
+
+    #[test]
+    fn archive_builder_add_raw_from_out_of_bounds() {
+        let source = pack(vec![("a", &b"a"[..])], Kind::BigF).unwrap();
+        let bogus = EntryInfo { offset: 1000, len: 10, name: "bogus".into(), index: 0 };
+
+        let mut builder = ArchiveBuilder::new(Kind::BigF);
+        assert_matches!(builder.add_raw_from(&source, &bogus), Err(Error::EntryOutOfBounds { .. }));
+    }
+
+    #[test]
+    fn archive_builder_limits() {
+        let limits = Limits { max_entry_size: Some(4), ..Limits::default() };
+
+        let mut builder = ArchiveBuilder::new(Kind::BigF);
+        builder.limits(limits).add("a", b"aaaa");
+        assert_eq!(builder.build().unwrap().limits(), limits);
+
+        builder.add("b", b"bbbbb");
+        assert_matches!(builder.build(), Err(Error::EntryTooLarge { size: 5, max: 4, .. }));
+    }
+
+    #[test]
+    fn plan_directory_matches_pack_directory() {
+        use std::fs;
+
+        let dir = temp_dir("plan");
+        fs::create_dir_all(dir.join("Data")).unwrap();
+        fs::write(dir.join("Data").join("a.ini"), b"aaa").unwrap();
+        fs::write(dir.join("b.ini"), b"bbbbbb").unwrap();
+
+        for &(store_metadata, store_checksums) in &[(false, false), (true, false), (false, true), (true, true)] {
+            let settings = Settings { store_checksums, ..settings_for(&dir, store_metadata) };
+            let plan = plan_directory(&dir, &settings).unwrap();
+            let archive = pack_directory(&dir, settings).unwrap();
+            let table = archive.read_entry_metadata_table().unwrap();
+
+            assert_eq!(plan.size.total(), archive.as_slice().len() as u64);
+            assert_eq!(plan.entries.len(), table.len());
+            for entry in &plan.entries {
+                assert_eq!(u64::from(table.get(&entry.name).unwrap().len), entry.size);
+            }
+
+            let secret_data = archive.read_secret_data(&table).unwrap().unwrap_or(&[]);
+            assert_eq!(Manifest::from_secret_data(secret_data).is_some(), store_metadata);
+            assert_eq!(archive.validate_with(&table, Level::WithChecksums).unwrap().is_valid(), store_checksums);
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pack_directory_limits() {
+        use std::fs;
+
+        let dir = temp_dir("limits");
+        fs::write(dir.join("a.ini"), b"a").unwrap();
+        fs::write(dir.join("b.ini"), b"b").unwrap();
+
+        let mut settings = settings_for(&dir, false);
+        settings.limits.max_entries = Some(1);
+        assert_matches!(pack_directory(&dir, settings), Err(Error::TooManyEntries { count: 2, max: 1 }));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn repack_default_is_identical() {
+        let source = pack_with_secret_data(vec![("b", &b"bb"[..]), ("a", &b"a"[..])], Kind::Big4, b"secret").unwrap();
+        let repacked = repack(&source, &RepackSettings::default()).unwrap();
+        assert_eq!(repacked.as_slice(), source.as_slice());
+    }
+
+    #[test]
+    fn repack_compact() {
+        use ::testing::{header_bytes, record_bytes};
+        use ::validation::Gap;
+
+        // "b" was replaced in place by a shorter version, leaving slack after both entries.
+        let mut bytes = header_bytes(48, 2, 36);
+        bytes.extend(record_bytes(36, 2, "a"));
+        bytes.extend(record_bytes(42, 2, "b"));
+        bytes.extend_from_slice(b"aa\0\0\0\0bb\0\0\0\0");
+
+        let source = Archive::from_bytes(&bytes).unwrap();
+        let table = source.read_entry_metadata_table().unwrap();
+        assert_eq!(source.find_gaps(&table).unwrap(), vec![Gap { start: 38, end: 42 }, Gap { start: 44, end: 48 }]);
+
+        let compacted = repack(&source, &RepackSettings::compact()).unwrap();
+        assert_eq!(compacted.as_slice().len(), 40);
+
+        let table = compacted.read_entry_metadata_table().unwrap();
+        assert!(compacted.find_gaps(&table).unwrap().is_empty());
+        assert_matches!(compacted.get_bytes_via_table(&table, "b"), Ok(Some(b)) if b == b"bb");
+    }
+
+    #[test]
+    fn repack_to_failing_writer() {
+        struct Full;
+
+        impl Write for Full {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::Error::other("no space left"))
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let source = pack(vec![("a", &b"a"[..])], Kind::BigF).unwrap();
+        assert_matches!(repack_to(&source, &RepackSettings::default(), &mut Full),
+            Err(Error::IO { ref operation, .. }) if operation == "writing the archive");
+    }
+
+    #[test]
+    fn repack_final_big() {
+        let entries = [("a", &b"aaaa"[..]), ("b", &b"bb"[..])];
+        let source = Archive::from_bytes(&::testing::final_big(&entries)).unwrap();
+
+        // The table ends after data_start so there is no secret data to carry over.
+        let repacked = repack(&source, &RepackSettings::default()).unwrap();
+        assert_eq!(repacked.as_slice(), &::testing::valid(&entries)[..]);
+
+        let compacted = repack(&source, &RepackSettings::compact()).unwrap();
+        let table = compacted.read_entry_metadata_table().unwrap();
+        assert!(compacted.validate(&table).unwrap().is_valid());
+        assert_matches!(compacted.get_bytes_via_table(&table, "b"), Ok(Some(b)) if b == b"bb");
+    }
+
+    #[test]
+    fn repack_settings() {
+        let source = pack_with_secret_data(vec![
+            ("Data/b.ini", &b"same"[..]),
+            ("Data\\a.ini", &b"same"[..]),
+            ("c.ini", &b"c"[..]),
+        ], Kind::Big4, b"secret").unwrap();
+
+        let settings = RepackSettings {
+            entry_order_criteria: Some(EntryOrderCriteria::Path),
+            kind: Some(Kind::BigF),
+            separator: Some(Separator::Backslash),
+            deduplicate: true,
+            alignment: 16,
+            strip_secret_data: true,
+            io: IoOptions { sequential: true, ..IoOptions::default() },
+        };
+
+        let archive = repack(&source, &settings).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+
+        assert_eq!(archive.read_kind().unwrap(), Kind::BigF);
+        assert_eq!(archive.read_secret_data(&table).unwrap(), None);
+
+        let a = table.get("Data\\a.ini").unwrap();
+        let b = table.get("Data\\b.ini").unwrap();
+        let c = table.get("c.ini").unwrap();
+        assert_eq!((a.index, b.index, c.index), (0, 1, 2));
+        assert_eq!(a.offset, b.offset);
+        assert_eq!(a.offset % 16, 0);
+        assert_eq!(c.offset % 16, 0);
+        assert_matches!(archive.get_bytes_via_entry(c), Ok(b"c"));
+        assert_eq!(archive.as_slice().len(), c.offset as usize + 1);
+    }
+
+    #[test]
+    fn repack_separator_collision() {
+        let source = pack(vec![("a/b", &b"1"[..]), ("a\\b", &b"2"[..])], Kind::BigF).unwrap();
+        let settings = RepackSettings { separator: Some(Separator::Slash), ..RepackSettings::default() };
+        assert_matches!(repack(&source, &settings), Err(Error::DuplicateEntry { .. }));
+    }
+
+    #[test]
+    fn pack_directory_is_reproducible() {
+        use std::fs;
+
+        // Same files, created in a different order so the directory walk differs.
+        let first = temp_dir("reproducible-1");
+        fs::create_dir_all(first.join("Data").join("INI")).unwrap();
+        fs::write(first.join("Data").join("INI").join("b.ini"), b"bb").unwrap();
+        fs::write(first.join("Data").join("a.ini"), b"aa").unwrap();
+        fs::write(first.join("c.ini"), b"c").unwrap();
+
+        let second = temp_dir("reproducible-2");
+        fs::write(second.join("c.ini"), b"c").unwrap();
+        fs::create_dir_all(second.join("Data").join("INI")).unwrap();
+        fs::write(second.join("Data").join("a.ini"), b"aa").unwrap();
+        fs::write(second.join("Data").join("INI").join("b.ini"), b"bb").unwrap();
+
+        for &order in &[EntryOrderCriteria::Path, EntryOrderCriteria::SmallestToLargest] {
+            let pack_dir = |dir: &Path| {
+                let settings = Settings { entry_order_criteria: order, ..settings_for(dir, false) };
+                pack_directory(dir, settings).unwrap()
+            };
+
+            let archive = pack_dir(&first);
+            assert_eq!(archive.as_slice(), pack_dir(&second).as_slice());
+
+            let names = archive.read_entry_metadata_table().unwrap()
+                .keys()
+                .cloned()
+                .collect::<Vec<_>>();
+
+            let expected = match order {
+                EntryOrderCriteria::Path => vec!["Data\\INI\\b.ini", "Data\\a.ini", "c.ini"],
+                EntryOrderCriteria::SmallestToLargest => vec!["c.ini", "Data\\INI\\b.ini", "Data\\a.ini"],
+            };
+            assert_eq!(names, expected);
+        }
+
+        fs::remove_dir_all(&first).unwrap();
+        fs::remove_dir_all(&second).unwrap();
+    }
+
+    #[test]
+    fn pack_directory_separator() {
+        use std::fs;
+
+        let dir = temp_dir("separator");
+        fs::create_dir_all(dir.join("Data")).unwrap();
+        fs::write(dir.join("Data").join("a.ini"), b"a").unwrap();
+
+        let settings = Settings { separator: Separator::Slash, ..settings_for(&dir, false) };
+        let archive = pack_directory(&dir, settings).unwrap();
+        assert!(archive.read_entry_metadata_table().unwrap().contains_key("Data/a.ini"));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pack_directory_markers() {
+        use std::fs;
+
+        let dir = temp_dir("markers");
+        fs::create_dir_all(dir.join("Data").join("INI")).unwrap();
+        fs::create_dir_all(dir.join("Empty")).unwrap();
+        fs::write(dir.join("Data").join("INI").join("a.ini"), b"a").unwrap();
+        fs::write(dir.join("empty.ini"), b"").unwrap();
+
+        let archive = pack_directory(&dir, settings_for(&dir, true)).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        let names = table.values().map(|e| e.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Data\\INI\\a.ini", "empty.ini"]);
+
+        let settings = Settings { directory_markers: true, ..settings_for(&dir, true) };
+        let size = plan_directory(&dir, &settings).unwrap().size;
+        let archive = pack_directory(&dir, settings).unwrap();
+        assert_eq!(size.total(), archive.as_slice().len() as u64);
+
+        let table = archive.read_entry_metadata_table().unwrap();
+        let names = table.values().map(|e| e.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["Data\\", "Data\\INI\\", "Data\\INI\\a.ini", "Empty\\", "empty.ini"]);
+        assert!(table.values().filter(|e| e.is_directory_marker()).all(|e| e.len == 0));
+
+        let manifest = Manifest::from_secret_data(archive.read_secret_data(&table).unwrap().unwrap()).unwrap();
+        assert_eq!(manifest.len(), 2);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pack_directory_inherits_order_and_secret_data() {
+        use std::fs;
+
+        let dir = temp_dir("inherit");
+        for name in &["a.ini", "b.ini", "c.ini", "new.ini"] {
+            fs::write(dir.join(name), name.as_bytes()).unwrap();
+        }
+
+        let stale = Checksums::compute(vec![("gone.ini", &b""[..])]).to_bytes();
+        let secret_data = secret_data::write_sections(&[
+            Section { tag: *b"MINE", payload: b"kept" },
+            Section { tag: Checksums::SECTION_TAG, payload: &stale },
+        ]);
+
+        let settings = Settings {
+            preferred_order: vec!["c.ini".to_string(), "gone.ini".to_string(), "a.ini".to_string()],
+            secret_data,
+            store_checksums: true,
+            ..settings_for(&dir, false)
+        };
+
+        let size = plan_directory(&dir, &settings).unwrap().size;
+        let archive = pack_directory(&dir, settings).unwrap();
+        assert_eq!(size.total(), archive.as_slice().len() as u64);
+
+        let table = archive.read_entry_metadata_table().unwrap();
+        let names = table.values().map(|e| e.name.as_str()).collect::<Vec<_>>();
+        assert_eq!(names, vec!["c.ini", "a.ini", "b.ini", "new.ini"]);
+
+        let secret_data = archive.read_secret_data(&table).unwrap().unwrap();
+        assert_eq!(secret_data::find_section(secret_data, b"MINE"), Some(&b"kept"[..]));
+        let checksums = Checksums::from_secret_data(secret_data).unwrap();
+        assert_eq!(checksums.len(), 4);
+        assert_eq!(checksums.get("gone.ini"), None);
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pack_directory_inherit_from_final_big() {
+        use std::fs;
+
+        let dir = temp_dir("inherit-final-big");
+        fs::write(dir.join("a.ini"), b"new").unwrap();
+
+        // What `easage pack --inherit-secret-data` does with an existing output.
+        let output = Archive::from_bytes(&::testing::final_big(&[("a.ini", b"old")])).unwrap();
+        let table = output.read_entry_metadata_table().unwrap();
+        let settings = Settings {
+            kind: output.read_kind().unwrap(),
+            preferred_order: table.values().map(|e| e.name.clone()).collect(),
+            secret_data: output.read_secret_data(&table).unwrap().map(|s| s.to_vec()).unwrap_or_default(),
+            ..settings_for(&dir, false)
+        };
+
+        let archive = pack_directory(&dir, settings).unwrap();
+        let table = archive.read_entry_metadata_table().unwrap();
+        assert_eq!(archive.read_secret_data(&table).unwrap(), None);
+        assert_matches!(archive.get_bytes_via_table(&table, "a.ini"), Ok(Some(b)) if b == b"new");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn pack_other_kind() {
+        let archive = pack(vec![("a", &b"a"[..])], Kind::Other(*b"MYFK")).unwrap();
+
+        assert_eq!(&archive.as_slice()[..4], b"MYFK");
+        assert_matches!(archive.read_kind(), Err(Error::InvalidMagic { .. }));
+
+        let archive = archive.with_lenient_magic(true);
+        assert_eq!(archive.read_kind().unwrap(), Kind::Other(*b"MYFK"));
+
+        // Repacking keeps the custom magic.
+        let repacked = repack(&archive, &RepackSettings::default()).unwrap();
+        assert_eq!(repacked.as_slice(), archive.as_slice());
+    }
+
+    #[test]
+    fn estimate_matches_pack() {
+        let entries = vec![("Data\\a.ini", &b"aaa"[..]), ("b.ini", &b""[..]), ("c.ini", &b"cccccc"[..])];
+        let sizes = || entries.iter().map(|&(name, data)| (name, data.len() as u64));
+
+        for &store_checksums in &[false, true] {
+            let settings = Settings { store_checksums, ..Settings::default() };
+            let report = estimate(sizes(), &settings);
+
+            let mut builder = ArchiveBuilder::new(Kind::BigF);
+            for &(name, data) in &entries {
+                builder.add(name, data);
+            }
+
+            let archive = builder.checksums(store_checksums).build().unwrap();
+            let table = archive.read_entry_metadata_table().unwrap();
+
+            assert_eq!(report.total(), archive.as_slice().len() as u64);
+            assert_eq!(report.data_start(), u64::from(archive.read_data_start().unwrap()));
+            assert_eq!(report.secret_data, archive.read_secret_data(&table).unwrap().map_or(0, |s| s.len() as u64));
+            assert_eq!((report.data, report.padding), (9, 0));
+        }
+    }
+
+    #[test]
+    fn size_report_padding() {
+        let entries = vec![("a", &b"aaa"[..]), ("empty", &b""[..]), ("b", &b"b"[..])];
+        let report = size_report(entries.iter().map(|&(name, data)| (name, data.len() as u64)), 0, 16);
+
+        let settings = RepackSettings { alignment: 16, ..RepackSettings::default() };
+        let archive = repack(&pack(entries, Kind::BigF).unwrap(), &settings).unwrap();
+
+        assert_eq!(report.total(), archive.as_slice().len() as u64);
+        // Data starts at 50, so "a" is padded to 64 and "b" from 67 to 80.
+        assert_eq!(report.data_start(), 50);
+        assert_eq!(report.padding, 14 + 13);
+    }
+}
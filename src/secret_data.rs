@@ -0,0 +1,99 @@
+//! Structured use of the "secret data" region between the table and `data_start`.
+//!
+//! The games never read this region so easage uses it to store optional,
+//! easage-specific information as a sequence of tagged sections:
+//!
+//! ```text
+//! tag: [u8; 4] | len: u32 (little-endian) | payload: [u8; len]
+//! ```
+//!
+//! Secret data written by other tools will almost certainly not parse as
+//! sections, in which case `read_sections` simply returns nothing.
+
+use byteorder::{ByteOrder, LittleEndian};
+
+/// The length of a section's tag and length.
+pub const SECTION_HEADER_LEN: usize = 4 + 4;
+
+/// A single tagged chunk of secret data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Section<'a> {
+    pub tag: [u8; 4],
+    pub payload: &'a [u8],
+}
+
+/// Parse `data` into sections.
+///
+/// Returns an empty `Vec` if `data` is not (entirely) made up of well-formed sections.
+pub fn read_sections(data: &[u8]) -> Vec<Section<'_>> {
+    let mut sections = vec![];
+    let mut pos = 0;
+
+    while pos < data.len() {
+        if data.len() - pos < SECTION_HEADER_LEN {
+            return vec![];
+        }
+
+        let mut tag = [0; 4];
+        tag.copy_from_slice(&data[pos..pos + 4]);
+        let len = LittleEndian::read_u32(&data[pos + 4..pos + SECTION_HEADER_LEN]) as usize;
+        pos += SECTION_HEADER_LEN;
+
+        if data.len() - pos < len {
+            return vec![];
+        }
+
+        sections.push(Section { tag, payload: &data[pos..pos + len] });
+        pos += len;
+    }
+
+    sections
+}
+
+/// The payload of the first section tagged `tag`, if any.
+pub fn find_section<'a>(data: &'a [u8], tag: &[u8; 4]) -> Option<&'a [u8]> {
+    read_sections(data).into_iter()
+        .find(|section| section.tag == *tag)
+        .map(|section| section.payload)
+}
+
+/// Serialize `sections` so they can be passed to `packer::pack_with_secret_data`.
+pub fn write_sections(sections: &[Section]) -> Vec<u8> {
+    let len = sections.iter().map(|s| SECTION_HEADER_LEN + s.payload.len()).sum();
+    let mut buf = Vec::with_capacity(len);
+
+    for section in sections {
+        let mut len_bytes = [0; 4];
+        LittleEndian::write_u32(&mut len_bytes, section.payload.len() as u32);
+
+        buf.extend_from_slice(&section.tag);
+        buf.extend_from_slice(&len_bytes);
+        buf.extend_from_slice(section.payload);
+    }
+
+    buf
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sections_round_trip() {
+        let sections = vec![
+            Section { tag: *b"AAAA", payload: b"first" },
+            Section { tag: *b"BBBB", payload: b"" },
+        ];
+
+        let data = write_sections(&sections);
+        assert_eq!(read_sections(&data), sections);
+        assert_eq!(find_section(&data, b"AAAA"), Some(&b"first"[..]));
+        assert_eq!(find_section(&data, b"CCCC"), None);
+    }
+
+    #[test]
+    fn sections_foreign_data() {
+        assert!(read_sections(b"FinalBig was here").is_empty());
+        assert!(read_sections(b"AAAA\xff\0\0\0short").is_empty());
+    }
+}
@@ -0,0 +1,355 @@
+use std::collections::HashSet;
+use std::iter::FromIterator;
+use std::ops::Index;
+use std::slice;
+
+use ::byteorder::{ByteOrder, LittleEndian};
+
+use ::EntryInfo;
+
+/// A map from entry name to metadata present in the header of an Archive.
+///
+/// Entries are stored in a `Vec` in table order (by `index`) alongside an
+/// open-addressing index of positions into that `Vec`. Compared to a
+/// `HashMap<String, EntryInfo>` this needs one allocation per entry instead
+/// of two and a much cheaper hash, which makes
+/// `Archive::read_entry_metadata_table` over twice as fast on archives with
+/// tens of thousands of entries (see `benches/table.rs`).
+///
+/// If several entries share a name the one that appears last in the
+/// archive's table (the one with the greatest `index`) is the one looked up
+/// by name. The others are still available from `shadowed` and `records`.
+#[derive(Debug, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+#[cfg_attr(feature = "serde", serde(from = "Vec<EntryInfo>", into = "Vec<EntryInfo>"))]
+pub struct EntryInfoTable {
+    entries: Vec<EntryInfo>,
+
+    /// Positions into `entries`, `EMPTY` for unused slots.
+    ///
+    /// The length is always 0 or a power of two greater than `entries.len()`.
+    slots: Vec<u32>,
+
+    /// Entries hidden by a later entry of the same name, in table order.
+    shadowed: Vec<EntryInfo>,
+
+    /// Where the records end in the archive the table was read from.
+    table_end: Option<usize>,
+}
+
+const EMPTY: u32 = u32::MAX;
+
+impl EntryInfoTable {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Create a table from `entries` in any order.
+    pub fn from_entries(mut entries: Vec<EntryInfo>) -> Self {
+        entries.sort_by_key(|e| e.index);
+
+        let mut table = EntryInfoTable { entries, ..Self::default() };
+        if !table.rebuild_slots() {
+            // Only index the last of the entries that share a name. This is
+            // rare enough that we do not mind building the index twice.
+            let keep = table.slots.iter()
+                .filter(|&&slot| slot != EMPTY)
+                .map(|&slot| slot as usize)
+                .collect::<HashSet<_>>();
+
+            let (kept, shadowed) = table.entries.drain(..)
+                .enumerate()
+                .partition::<Vec<_>, _>(|&(position, _)| keep.contains(&position));
+
+            table.entries = kept.into_iter().map(|(_, e)| e).collect();
+            table.shadowed = shadowed.into_iter().map(|(_, e)| e).collect();
+            table.rebuild_slots();
+        }
+
+        table
+    }
+
+    /// Returns `false` if some entries were shadowed by later ones of the same name.
+    fn rebuild_slots(&mut self) -> bool {
+        let slot_count = (self.entries.len() * 2).next_power_of_two();
+        self.slots = vec![EMPTY; slot_count];
+
+        let mut is_unique = true;
+        for (position, entry) in self.entries.iter().enumerate() {
+            let slot = self.find_slot(&entry.name);
+
+            is_unique &= self.slots[slot] == EMPTY;
+            self.slots[slot] = position as u32;
+        }
+
+        is_unique
+    }
+
+    /// The slot that holds `name`, or the empty slot it would be stored in.
+    fn find_slot(&self, name: &str) -> usize {
+        // The high bits of `hash` are the well mixed ones.
+        let mask = self.slots.len() - 1;
+        let bits = self.slots.len().trailing_zeros();
+        let mut slot = (hash(name.as_bytes()).checked_shr(64 - bits).unwrap_or(0)) as usize;
+
+        loop {
+            match self.slots[slot] {
+                EMPTY => return slot,
+                position if self.entries[position as usize].name == name => return slot,
+                _ => slot = (slot + 1) & mask,
+            }
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&EntryInfo> {
+        if self.slots.is_empty() {
+            return None;
+        }
+
+        match self.slots[self.find_slot(name)] {
+            EMPTY => None,
+            position => Some(&self.entries[position as usize]),
+        }
+    }
+
+    pub fn contains_key(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The entries in table order.
+    pub fn values(&self) -> slice::Iter<'_, EntryInfo> {
+        self.entries.iter()
+    }
+
+    /// The entries that are not looked up by name because a later entry has
+    /// the same name, in table order.
+    pub fn shadowed(&self) -> &[EntryInfo] {
+        &self.shadowed
+    }
+
+    /// Every record of the table, including shadowed ones, in table order.
+    pub fn records(&self) -> Vec<&EntryInfo> {
+        let mut records = self.entries.iter().chain(&self.shadowed).collect::<Vec<_>>();
+        records.sort_by_key(|e| e.index);
+        records
+    }
+
+    /// The record at position `index` in the table, even if it is shadowed.
+    pub fn record(&self, index: usize) -> Option<&EntryInfo> {
+        let find = |entries: &'_ [EntryInfo]| entries.binary_search_by_key(&index, |e| e.index).ok();
+
+        find(&self.entries).map(|position| &self.entries[position])
+            .or_else(|| find(&self.shadowed).map(|position| &self.shadowed[position]))
+    }
+
+    /// The offset one past the last record in the archive this table was
+    /// read from, if it was read from an archive.
+    pub fn table_end(&self) -> Option<usize> {
+        self.table_end
+    }
+
+    pub(crate) fn set_table_end(&mut self, table_end: usize) {
+        self.table_end = Some(table_end);
+    }
+
+    /// The entry names in table order.
+    pub fn keys(&self) -> impl Iterator<Item = &String> {
+        self.entries.iter().map(|e| &e.name)
+    }
+
+    /// Pairs of entry name and entry in table order.
+    pub fn iter(&self) -> impl Iterator<Item = (&String, &EntryInfo)> {
+        self.entries.iter().map(|e| (&e.name, e))
+    }
+
+    /// The entries of this table whose names are also in `other`, in table order.
+    ///
+    /// Names are compared exactly (see `archive_set::normalize_name` to
+    /// compare them the way the game does).
+    pub fn intersection<'a>(&'a self, other: &'a EntryInfoTable) -> impl Iterator<Item = &'a EntryInfo> {
+        self.intersection_by(other, |_, _| true)
+    }
+
+    /// The entries of this table whose names are not in `other`, in table order.
+    pub fn difference<'a>(&'a self, other: &'a EntryInfoTable) -> impl Iterator<Item = &'a EntryInfo> {
+        self.difference_by(other, |_, _| true)
+    }
+
+    /// The entries of this table followed by the entries of `other` whose
+    /// names are not in this table.
+    pub fn union<'a>(&'a self, other: &'a EntryInfoTable) -> impl Iterator<Item = &'a EntryInfo> {
+        self.values().chain(other.difference(self))
+    }
+
+    /// The entries of this table for which `other` has an entry of the same
+    /// name and `same(entry, other_entry)` returns `true`.
+    ///
+    /// Tables only describe entries so to also compare their data look it up
+    /// in the archives the tables were read from:
+    ///
+    /// ```rust,no_run
+    /// use easage::Archive;
+    ///
+    /// let old = Archive::from_path("old.big").unwrap();
+    /// let new = Archive::from_path("new.big").unwrap();
+    /// let (old_table, new_table) = (old.table().unwrap(), new.table().unwrap());
+    ///
+    /// let added_or_changed = new_table.difference_by(old_table, |n, o| {
+    ///     new.get_bytes_via_entry(n).ok() == old.get_bytes_via_entry(o).ok()
+    /// });
+    /// ```
+    pub fn intersection_by<'a, F>(&'a self, other: &'a EntryInfoTable, same: F) -> impl Iterator<Item = &'a EntryInfo>
+        where F: Fn(&EntryInfo, &EntryInfo) -> bool + 'a {
+        self.values()
+            .filter(move |entry| other.get(&entry.name).is_some_and(|o| same(entry, o)))
+    }
+
+    /// The entries of this table that are not in `intersection_by(other, same)`.
+    pub fn difference_by<'a, F>(&'a self, other: &'a EntryInfoTable, same: F) -> impl Iterator<Item = &'a EntryInfo>
+        where F: Fn(&EntryInfo, &EntryInfo) -> bool + 'a {
+        self.values()
+            .filter(move |entry| !other.get(&entry.name).is_some_and(|o| same(entry, o)))
+    }
+}
+
+impl PartialEq for EntryInfoTable {
+    fn eq(&self, other: &Self) -> bool {
+        self.entries == other.entries && self.shadowed == other.shadowed
+    }
+}
+
+impl Index<&str> for EntryInfoTable {
+    type Output = EntryInfo;
+
+    /// # Panics
+    ///
+    /// If there is no entry named `name`.
+    fn index(&self, name: &str) -> &EntryInfo {
+        self.get(name).expect("no entry found for name")
+    }
+}
+
+/// The hash used by rustc (FxHash) which is more than good enough for entry
+/// names and, working a word at a time, much cheaper than the `HashMap` default.
+fn hash(bytes: &[u8]) -> u64 {
+    const SEED: u64 = 0x51_7C_C1_B7_27_22_0A_95;
+
+    let add = |hash: u64, word: u64| (hash.rotate_left(5) ^ word).wrapping_mul(SEED);
+
+    let mut chunks = bytes.chunks_exact(8);
+    let mut hash = (&mut chunks).fold(0, |hash, chunk| add(hash, LittleEndian::read_u64(chunk)));
+
+    let rest = chunks.remainder();
+    if !rest.is_empty() {
+        let mut word = [0; 8];
+        word[..rest.len()].copy_from_slice(rest);
+        hash = add(hash, LittleEndian::read_u64(&word) ^ rest.len() as u64);
+    }
+
+    hash
+}
+
+impl From<Vec<EntryInfo>> for EntryInfoTable {
+    fn from(entries: Vec<EntryInfo>) -> Self {
+        Self::from_entries(entries)
+    }
+}
+
+impl From<EntryInfoTable> for Vec<EntryInfo> {
+    /// Every record, including shadowed ones, in table order.
+    fn from(table: EntryInfoTable) -> Self {
+        let mut records = table.entries;
+        records.extend(table.shadowed);
+        records.sort_by_key(|e| e.index);
+        records
+    }
+}
+
+impl FromIterator<EntryInfo> for EntryInfoTable {
+    fn from_iter<I: IntoIterator<Item = EntryInfo>>(iter: I) -> Self {
+        Self::from_entries(iter.into_iter().collect())
+    }
+}
+
+impl<'a> IntoIterator for &'a EntryInfoTable {
+    type Item = &'a EntryInfo;
+    type IntoIter = slice::Iter<'a, EntryInfo>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.values()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(name: &str, index: usize) -> EntryInfo {
+        EntryInfo { offset: index as u32, len: 0, name: name.to_string(), index }
+    }
+
+    #[test]
+    fn table_get() {
+        let table = EntryInfoTable::from_entries(vec![entry("c", 0), entry("a", 1), entry("b", 2)]);
+
+        assert_eq!(table.len(), 3);
+        assert_eq!(table.get("a"), Some(&entry("a", 1)));
+        assert_eq!(table.get("c"), Some(&entry("c", 0)));
+        assert_eq!(table.get("d"), None);
+        assert_eq!(table["b"], entry("b", 2));
+        assert_eq!(table.keys().collect::<Vec<_>>(), vec!["c", "a", "b"]);
+    }
+
+    #[test]
+    fn table_empty() {
+        let table = EntryInfoTable::new();
+        assert!(table.is_empty());
+        assert_eq!(table.get("a"), None);
+    }
+
+    #[test]
+    fn table_duplicates_keep_last() {
+        let table = vec![entry("a", 0), entry("b", 1), entry("a", 2), entry("a", 3)]
+            .into_iter()
+            .collect::<EntryInfoTable>();
+
+        assert_eq!(table.len(), 2);
+        assert_eq!(table.get("a"), Some(&entry("a", 3)));
+        assert_eq!(table.values().cloned().collect::<Vec<_>>(), vec![entry("b", 1), entry("a", 3)]);
+
+        assert_eq!(table.shadowed(), &[entry("a", 0), entry("a", 2)][..]);
+        assert_eq!(table.records(), vec![&entry("a", 0), &entry("b", 1), &entry("a", 2), &entry("a", 3)]);
+        assert_eq!(table.record(2), Some(&entry("a", 2)));
+        assert_eq!(table.record(3), Some(&entry("a", 3)));
+        assert_eq!(table.record(4), None);
+    }
+
+    fn names<'a, I: Iterator<Item = &'a EntryInfo>>(entries: I) -> Vec<&'a str> {
+        entries.map(|e| e.name.as_str()).collect()
+    }
+
+    #[test]
+    fn table_set_operations() {
+        let a = EntryInfoTable::from_entries(vec![entry("x", 0), entry("y", 1), entry("z", 2)]);
+        let b = EntryInfoTable::from_entries(vec![entry("z", 0), entry("y", 1), entry("w", 2)]);
+
+
+        assert_eq!(names(a.intersection(&b)), vec!["y", "z"]);
+        assert_eq!(names(a.difference(&b)), vec!["x"]);
+        assert_eq!(names(a.union(&b)), vec!["x", "y", "z", "w"]);
+        assert_eq!(a.union(&b).find(|e| e.name == "y"), Some(&entry("y", 1)));
+
+        // `entry` uses the index as the offset, so only "y" is at the same offset in both.
+        let same_offset = |x: &EntryInfo, y: &EntryInfo| x.offset == y.offset;
+        assert_eq!(names(a.intersection_by(&b, same_offset)), vec!["y"]);
+        assert_eq!(names(a.difference_by(&b, same_offset)), vec!["x", "z"]);
+    }
+}
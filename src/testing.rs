@@ -0,0 +1,87 @@
+//! Helpers to build valid and pathological archives in memory.
+//!
+//! Used by easage's own tests and available to other crates (with the
+//! `test-util` feature) that want to test how they handle broken archives.
+//!
+//! Every function returns raw bytes so they can be modified further before
+//! being passed to `Archive::from_bytes`.
+
+use byteorder::{BigEndian, LittleEndian, ReadBytesExt, WriteBytesExt};
+
+use ::{packer, Kind};
+
+/// A `BIGF` header with the given fields.
+pub fn header_bytes(size: u32, len: u32, data_start: u32) -> Vec<u8> {
+    let mut bytes = b"BIGF".to_vec();
+    bytes.write_u32::<LittleEndian>(size).unwrap();
+    bytes.write_u32::<BigEndian>(len).unwrap();
+    bytes.write_u32::<BigEndian>(data_start).unwrap();
+    bytes
+}
+
+/// A single table record.
+pub fn record_bytes(offset: u32, len: u32, name: &str) -> Vec<u8> {
+    let mut bytes = vec![];
+    bytes.write_u32::<BigEndian>(offset).unwrap();
+    bytes.write_u32::<BigEndian>(len).unwrap();
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.push(0);
+    bytes
+}
+
+/// A well-formed `BIGF` archive containing `entries`.
+///
+/// # Panics
+///
+/// If `entries` can not be packed (see `packer::pack`).
+pub fn valid(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    packer::pack(entries.to_vec(), Kind::BigF)
+        .expect("entries could not be packed")
+        .as_slice()
+        .to_vec()
+}
+
+/// The first `len` bytes of `valid(entries)`.
+pub fn truncated(entries: &[(&str, &[u8])], len: usize) -> Vec<u8> {
+    let mut bytes = valid(entries);
+    bytes.truncate(len);
+    bytes
+}
+
+/// `valid(entries)` with `declared_size` in the header instead of the actual size.
+pub fn mis_sized(entries: &[(&str, &[u8])], declared_size: u32) -> Vec<u8> {
+    let mut bytes = valid(entries);
+    (&mut bytes[4..8]).write_u32::<LittleEndian>(declared_size).unwrap();
+    bytes
+}
+
+/// An archive whose entries `a` (4 bytes) and `b` (2 bytes) share their last
+/// 2 bytes of data, at offsets 38 to 40.
+pub fn overlapping() -> Vec<u8> {
+    let mut bytes = header_bytes(40, 2, 36);
+    bytes.extend(record_bytes(36, 4, "a"));
+    bytes.extend(record_bytes(38, 2, "b"));
+    bytes.extend_from_slice(&[0; 4]);
+    bytes
+}
+
+/// `valid(entries)` with a `data_start` 1 byte less than the end of the table,
+/// as FinalBig writes it.
+pub fn final_big(entries: &[(&str, &[u8])]) -> Vec<u8> {
+    let mut bytes = valid(entries);
+    let data_start = (&bytes[12..16]).read_u32::<BigEndian>().unwrap();
+    (&mut bytes[12..16]).write_u32::<BigEndian>(data_start - 1).unwrap();
+    bytes
+}
+
+/// An archive with two records named `a` (the first one shadowed), one named
+/// `b`, and the secret data `secret` after the table.
+pub fn duplicate_names() -> Vec<u8> {
+    let mut bytes = header_bytes(55, 3, 52);
+    bytes.extend(record_bytes(52, 1, "a"));
+    bytes.extend(record_bytes(53, 1, "b"));
+    bytes.extend(record_bytes(54, 1, "a"));
+    bytes.extend_from_slice(b"secret");
+    bytes.extend_from_slice(b"xba");
+    bytes
+}
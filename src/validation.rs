@@ -0,0 +1,288 @@
+//! Structural checks that go beyond what is required to read an archive.
+//!
+//! Start with `Archive::validate`.
+
+use ::EntryInfoTable;
+use ::checksums::Checksums;
+use ::crc32;
+
+/// How thorough `Archive::validate_with` is.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum Level {
+    /// Only check the header and table against the archive's size (cheap).
+    Structure,
+
+    /// Also read every entry and compare it against the `Checksums` stored
+    /// in the archive's secret data.
+    WithChecksums,
+}
+
+/// A single problem found while validating an archive.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub enum Issue {
+    /// The size declared in the header does not match the actual size of the archive.
+    SizeMismatch {
+        declared: u32,
+        actual: usize,
+    },
+
+    /// The entry's data lies (partially) outside of the archive.
+    EntryOutOfBounds {
+        name: String,
+        start: usize,
+        end: usize,
+    },
+
+    /// The entry's data starts before `data_start`, overlapping the header or table.
+    OverlapsTable {
+        name: String,
+        start: usize,
+        data_start: usize,
+    },
+
+    /// The data of two entries share the byte range `start..end`.
+    Overlap {
+        first: String,
+        second: String,
+        start: usize,
+        end: usize,
+    },
+
+    /// The entry's name marks it as a directory (see
+    /// `EntryInfo::is_directory_marker`) but it has data.
+    DirectoryMarkerWithData {
+        name: String,
+        len: u32,
+    },
+
+    /// Checksums were requested but the archive does not store any.
+    NoChecksums,
+
+    /// The table ends after `data_start` (as in archives written by
+    /// FinalBig), leaving no room for secret data such as checksums.
+    TableEndsAfterDataStart {
+        table_end: usize,
+        data_start: usize,
+    },
+
+    /// The archive stores checksums but none for this entry.
+    ChecksumMissing {
+        name: String,
+    },
+
+    /// The entry's data does not match the stored checksum.
+    ChecksumMismatch {
+        name: String,
+        expected: u32,
+        actual: u32,
+    },
+}
+
+/// The result of `Archive::validate`.
+///
+/// An empty report means no problems were found.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct ValidationReport {
+    pub issues: Vec<Issue>,
+}
+
+impl ValidationReport {
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+
+    /// Only the `Issue::Overlap` and `Issue::OverlapsTable` issues.
+    pub fn overlaps(&self) -> impl Iterator<Item = &Issue> {
+        self.issues.iter().filter(|issue| matches!(**issue, Issue::Overlap { .. } | Issue::OverlapsTable { .. }))
+    }
+}
+
+/// Find entries whose data overlaps another entry or the region before `data_start`.
+///
+/// Zero-length entries never overlap anything.
+///
+/// Each overlapping entry is reported once, paired with the preceding entry
+/// that extends furthest into the archive.
+pub fn find_overlaps(table: &EntryInfoTable, data_start: usize) -> Vec<Issue> {
+    let mut entries = table.values()
+        .filter(|e| e.len > 0)
+        .collect::<Vec<_>>();
+
+    entries.sort_by(|a, b| (a.offset, a.len, &a.name).cmp(&(b.offset, b.len, &b.name)));
+
+    let mut issues = vec![];
+    let mut widest: Option<(&str, usize)> = None;
+
+    for entry in entries {
+        let start = entry.offset as usize;
+        let end = start + entry.len as usize;
+
+        if start < data_start {
+            issues.push(Issue::OverlapsTable {
+                name: entry.name.clone(),
+                start,
+                data_start,
+            });
+        }
+
+        match widest {
+            Some((widest_name, widest_end)) if start < widest_end => {
+                issues.push(Issue::Overlap {
+                    first: widest_name.to_string(),
+                    second: entry.name.clone(),
+                    start,
+                    end: ::std::cmp::min(end, widest_end),
+                });
+
+                if end > widest_end {
+                    widest = Some((&entry.name, end));
+                }
+            },
+            _ => widest = Some((&entry.name, end)),
+        }
+    }
+
+    issues
+}
+
+/// A range of bytes after `data_start` that no entry's data covers, such as
+/// the space left behind when another tool replaces an entry in place.
+#[derive(Debug, Copy, Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(Serialize, Deserialize))]
+pub struct Gap {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Gap {
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
+
+/// Find the ranges between `data_start` and `archive_len` not covered by the
+/// data of any entry in `table`, in order of their offset.
+///
+/// Data outside of `data_start..archive_len` is ignored. Alignment padding
+/// is reported like any other gap.
+pub fn find_gaps(table: &EntryInfoTable, data_start: usize, archive_len: usize) -> Vec<Gap> {
+    let mut ranges = table.values()
+        .filter(|e| e.len > 0)
+        .map(|e| (e.offset as usize, ::std::cmp::min(e.offset as usize + e.len as usize, archive_len)))
+        .collect::<Vec<_>>();
+
+    ranges.sort();
+
+    let mut gaps = vec![];
+    let mut covered_until = data_start;
+
+    for (start, end) in ranges {
+        if start > covered_until {
+            gaps.push(Gap { start: covered_until, end: start });
+        }
+
+        covered_until = ::std::cmp::max(covered_until, end);
+    }
+
+    if archive_len > covered_until {
+        gaps.push(Gap { start: covered_until, end: archive_len });
+    }
+
+    gaps
+}
+
+/// Compare the data of every entry in `table` that lies inside of `data`
+/// (the whole archive) against `checksums`, in table order.
+pub fn verify_checksums(table: &EntryInfoTable, checksums: &Checksums, data: &[u8]) -> Vec<Issue> {
+    let mut issues = vec![];
+
+    for entry in table.values() {
+        let start = entry.offset as usize;
+        let end = start + entry.len as usize;
+        if end > data.len() {
+            // Already reported as `Issue::EntryOutOfBounds`.
+            continue;
+        }
+
+        let expected = match checksums.get(&entry.name) {
+            Some(expected) => expected,
+            None => {
+                issues.push(Issue::ChecksumMissing { name: entry.name.clone() });
+                continue;
+            },
+        };
+
+        let actual = crc32::checksum(&data[start..end]);
+        if actual != expected {
+            issues.push(Issue::ChecksumMismatch { name: entry.name.clone(), expected, actual });
+        }
+    }
+
+    issues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ::EntryInfo;
+
+    fn table(entries: &[(&str, u32, u32)]) -> EntryInfoTable {
+        entries.iter()
+            .enumerate()
+            .map(|(index, &(name, offset, len))| EntryInfo { offset, len, name: name.to_string(), index })
+            .collect()
+    }
+
+    #[test]
+    fn find_overlaps_none() {
+        let table = table(&[("a", 100, 10), ("b", 110, 10), ("c", 120, 0)]);
+        assert!(find_overlaps(&table, 100).is_empty());
+    }
+
+    #[test]
+    fn find_overlaps_entries() {
+        let table = table(&[("a", 100, 20), ("b", 110, 20), ("c", 125, 2)]);
+        let issues = find_overlaps(&table, 100);
+
+        assert_eq!(issues, vec![
+            Issue::Overlap { first: "a".into(), second: "b".into(), start: 110, end: 120 },
+            Issue::Overlap { first: "b".into(), second: "c".into(), start: 125, end: 127 },
+        ]);
+    }
+
+    #[test]
+    fn find_overlaps_table() {
+        let table = table(&[("a", 20, 10)]);
+        let issues = find_overlaps(&table, 100);
+
+        assert_eq!(issues, vec![
+            Issue::OverlapsTable { name: "a".into(), start: 20, data_start: 100 },
+        ]);
+    }
+
+    #[test]
+    fn find_overlaps_zero_length() {
+        let table = table(&[("a", 100, 20), ("b", 110, 0)]);
+        assert!(find_overlaps(&table, 100).is_empty());
+    }
+
+    #[test]
+    fn find_gaps_between_entries() {
+        let table = table(&[("a", 110, 10), ("b", 115, 10), ("c", 130, 0), ("d", 140, 5)]);
+
+        assert_eq!(find_gaps(&table, 100, 150), vec![
+            Gap { start: 100, end: 110 },
+            Gap { start: 125, end: 140 },
+            Gap { start: 145, end: 150 },
+        ]);
+
+        let table = self::table(&[("a", 100, 10), ("b", 110, 10)]);
+        assert!(find_gaps(&table, 100, 120).is_empty());
+    }
+}